@@ -1,54 +1,315 @@
 use crate::{
     chrono::{from_chrono, to_chrono},
-    interval::Interval,
+    interval::{Interval, TimeWindow},
+    solar::{self, Coordinates},
     tasks::TaskIdx,
     Scheduler,
 };
 use croner::Cron;
-use derive_more::{Deref, DerefMut, Into};
 use indexmap::IndexMap;
-use jiff::{civil::DateTime, tz::TimeZone, RoundMode, Span, ToSpan, Unit, ZonedRound};
-use std::{collections::BTreeMap, error::Error};
+use jiff::{
+    civil::{Date, Time},
+    tz::TimeZone,
+    Span, Timestamp, ToSpan, Unit,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
+#[derive(Default)]
 pub struct TaskAllocatorWithPlans {
     pub granularity: Span,
-    pub plans: BTreeMap<Interval, String>,
+    // smaller granularities to fall back to when a `granularity` block doesn't fit a gap
+    pub granularities: Vec<Span>,
+    pub plans: Plans,
+    // nothing is allocated within this span from the start of the scheduling interval
+    pub min_notice: Span,
+    // gaps shorter than this are skipped, unless the task's remaining work fits in them
+    pub min_session: Span,
+    // descriptions of plans that are `PlanKind::Soft`
+    pub soft_plans: BTreeSet<String>,
+    // time of day tasks may be scheduled in, the rest of each day is blocked like a plan
+    pub working_hours: Option<TimeWindow>,
+    // gap kept free after each work block before the next one, zero disables
+    pub break_span: Span,
+    pub daily_capacity: DailyCapacity,
+}
+
+// free hours of each local day, filled in by `TaskAllocatorWithPlans::capacity_on` as days are
+// asked for. cleared when the plans, the scheduling interval or the time zone change, and by
+// `TaskAllocatorWithPlans::reset`
+#[derive(Default)]
+pub struct DailyCapacity {
+    // plans generation, scheduling interval and time zone the days were computed for
+    built_for: RefCell<Option<(u64, Interval, TimeZone)>>,
+    days: RefCell<BTreeMap<Date, f32>>,
+}
+
+// soft plans still keep tasks out, but the deadline heuristic does not count them as taken time,
+// e.g. a gym session that would be skipped for an urgent task
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlanKind {
+    Hard,
+    Soft,
 }
 
 // allocates intervals for tasks with max length of `granularity`. avoids placing tasks on planed
-// intervals. if available interval is smaller than `granularity`, the task will reduce the interval
-// to fit it to available interval
+// intervals, already scheduled (e.g. pinned) intervals, outside of the working hours and outside
+// of the task's allowed window.
+// if available interval is smaller than `granularity`, the largest of `granularities` that fits
+// is used, otherwise the task will reduce the interval to fit it to available interval
 impl TaskAllocatorWithPlans {
+    pub fn plan_kind(&self, description: &str) -> PlanKind {
+        if self.soft_plans.contains(description) {
+            PlanKind::Soft
+        } else {
+            PlanKind::Hard
+        }
+    }
+
+    // the parts of `interval` outside of the working hours in `tz`, none if they are not set
+    pub fn outside_working_hours(&self, interval: &Interval, tz: &TimeZone) -> Vec<Interval> {
+        self.working_hours
+            .as_ref()
+            .map(|working_hours| working_hours.complement(interval, tz))
+            .unwrap_or_default()
+    }
+
+    // forgets the daily capacity, e.g. after changing `min_notice` or `working_hours`
+    pub fn reset(&mut self) {
+        self.daily_capacity = DailyCapacity::default();
+    }
+
+    // free hours of the local day `day`, see `compute_capacity_on`. cached per date
+    pub fn capacity_on(&self, scheduler: &Scheduler, day: &Interval) -> f32 {
+        let built_for = Some((
+            self.plans.generation,
+            scheduler.interval.clone(),
+            scheduler.tz.clone(),
+        ));
+        if *self.daily_capacity.built_for.borrow() != built_for {
+            self.daily_capacity.days.borrow_mut().clear();
+            *self.daily_capacity.built_for.borrow_mut() = built_for;
+        }
+        let date = day.start.to_zoned(scheduler.tz.clone()).date();
+        *self
+            .daily_capacity
+            .days
+            .borrow_mut()
+            .entry(date)
+            .or_insert_with(|| self.compute_capacity_on(scheduler, day))
+    }
+
+    // free hours of `day` within the scheduling interval, outside of plans, the notice period and
+    // the working hours
+    pub fn compute_capacity_on(&self, scheduler: &Scheduler, day: &Interval) -> f32 {
+        let Some(day) = day.clip_to(&scheduler.interval) else {
+            return 0.0;
+        };
+        let end = Interval::new(day.end, day.end);
+        let mut blocked: Vec<_> = self
+            .plans
+            .range(..end)
+            .map(|(plan, _)| plan)
+            .filter(|plan| plan.intercepts(&day))
+            .cloned()
+            .collect();
+        if self.min_notice.is_positive() {
+            blocked.push(Interval::from_span(
+                scheduler.interval.start,
+                self.min_notice,
+            ));
+        }
+        blocked.extend(self.outside_working_hours(&day, &scheduler.tz));
+        day.difference(&blocked).iter().map(Interval::hours).sum()
+    }
+
+    pub fn has_capacity_on(&self, scheduler: &Scheduler, day: &Interval) -> bool {
+        self.capacity_on(scheduler, day) > f32::EPSILON
+    }
+
     pub fn allocate(&self, scheduler: &Scheduler, task_idx: TaskIdx) -> Interval {
-        let mut allocated_interval = Interval::new(
-            scheduler.current_time,
-            scheduler.current_time + self.granularity,
+        let Some(task) = scheduler.task(task_idx) else {
+            return Interval::new(scheduler.current_time, scheduler.current_time);
+        };
+        let work_hours = task.volume - scheduler.get_total_task_hours(task_idx);
+        self.allocate_hours(scheduler, task_idx, work_hours)
+    }
+
+    // like `allocate`, for `work_hours` of the task instead of what is left of its volume
+    pub fn allocate_hours(
+        &self,
+        scheduler: &Scheduler,
+        task_idx: TaskIdx,
+        work_hours: f32,
+    ) -> Interval {
+        let Some(task) = scheduler.task(task_idx) else {
+            return Interval::new(scheduler.current_time, scheduler.current_time);
+        };
+
+        let mut blocked_intervals: Vec<_> = self.plans.keys().cloned().collect();
+        blocked_intervals.extend(
+            scheduler
+                .iter()
+                .flatten()
+                .filter(|interval| interval.end > scheduler.current_time)
+                .cloned(),
         );
+        if self.min_notice.is_positive() {
+            blocked_intervals.push(Interval::from_span(
+                scheduler.interval.start,
+                self.min_notice,
+            ));
+        }
+        // a break only follows work, the start of the day and the end of a plan need none. it
+        // ends with the day of its block, so late work does not push back the next morning
+        if self.break_span.is_positive() {
+            blocked_intervals.extend(
+                scheduler
+                    .iter()
+                    .flatten()
+                    .map(|interval| (interval, Interval::from_span(interval.end, self.break_span)))
+                    .filter(|(_, break_interval)| break_interval.end > scheduler.current_time)
+                    .filter_map(|(interval, break_interval)| {
+                        break_interval.clip_to(&scheduler.get_day(interval.start))
+                    }),
+            );
+        }
+        blocked_intervals.extend(scheduler.frozen_zone());
+        let ahead = Interval::new(scheduler.current_time, scheduler.interval.end);
+        blocked_intervals.extend(self.outside_working_hours(&ahead, &scheduler.tz));
+        if let Some(window) = &task.allowed_window {
+            blocked_intervals.extend(window.complement(&ahead, &scheduler.tz));
+        }
+        blocked_intervals.sort();
 
-        let task = &scheduler.tasks[task_idx];
-        let work_hours = task.volume - scheduler.get_total_task_hours(task_idx);
-        let work_span = ((work_hours * 3600.0) as i32).seconds();
+        // a task's own granularity replaces the global ones
+        let mut granularities = match task.granularity {
+            Some(granularity) => vec![granularity],
+            None => [self.granularity]
+                .into_iter()
+                .chain(self.granularities.iter().copied())
+                .collect(),
+        };
+        let granularity_hours = |granularity: &Span| {
+            granularity
+                .total(Unit::Hour)
+                .expect("Failed to get hours from granularity") as f32
+        };
+        granularities.sort_by(|a, b| granularity_hours(b).total_cmp(&granularity_hours(a)));
+
+        // blocks of each granularity, with whether they kept their full length
+        let blocks: Vec<_> = granularities
+            .iter()
+            .map(|granularity| {
+                let block_hours = work_hours.min(granularity_hours(granularity));
+                let block =
+                    self.allocate_session(scheduler, work_hours, *granularity, &blocked_intervals);
+                let is_full = (block.hours() - block_hours).abs() < 1.0 / 3600.0;
+                (block, is_full)
+            })
+            .collect();
+
+        let earliest_start = blocks
+            .iter()
+            .map(|(block, _)| block.start)
+            .min()
+            .expect("Failed to find earliest block");
+        let earliest_blocks: Vec<_> = blocks
+            .into_iter()
+            .filter(|(block, _)| block.start == earliest_start)
+            .collect();
+
+        earliest_blocks
+            .iter()
+            .find(|(_, is_full)| *is_full)
+            .unwrap_or(&earliest_blocks[0])
+            .0
+            .clone()
+    }
+
+    // the first block from `current_time` that is at least `min_session` long. shrinking to the
+    // remaining work takes precedence, so the final stub of a task is never held back
+    fn allocate_session(
+        &self,
+        scheduler: &Scheduler,
+        work_hours: f32,
+        granularity: Span,
+        blocked_intervals: &[Interval],
+    ) -> Interval {
+        let min_session_hours = self
+            .min_session
+            .total(Unit::Hour)
+            .expect("Failed to get hours from min session") as f32;
+        // days without any free time are skipped as a whole
+        let mut day = scheduler.get_day(scheduler.current_time);
+        while day.end < scheduler.interval.end && !self.has_capacity_on(scheduler, &day) {
+            day = scheduler.get_day(day.end);
+        }
+        let mut start = scheduler.current_time.max(day.start);
+        loop {
+            let block =
+                self.allocate_block(scheduler, start, work_hours, granularity, blocked_intervals);
+            let is_long_enough = block.hours() >= min_session_hours - 1.0 / 3600.0;
+            let is_final = block.hours() >= work_hours - 1.0 / 3600.0;
+            if is_long_enough
+                || is_final
+                || block.end <= start
+                || block.end >= scheduler.interval.end
+            {
+                return block;
+            }
+            start = block.end;
+        }
+    }
+
+    fn allocate_block(
+        &self,
+        scheduler: &Scheduler,
+        start: Timestamp,
+        work_hours: f32,
+        granularity: Span,
+        blocked_intervals: &[Interval],
+    ) -> Interval {
+        let mut allocated_interval = Interval::new(start, start + granularity);
+
+        // capped to what is left of the window, so huge volumes cannot overflow the span
+        let window_seconds = (scheduler.interval.end.as_second() - start.as_second()).max(0);
+        let work_span = ((work_hours * 3600.0).round() as i64)
+            .min(window_seconds)
+            .seconds();
 
         if work_hours
-            <= self
-                .granularity
+            <= granularity
                 .total(Unit::Hour)
                 .expect("Failed to get hours from granularity") as f32
         {
             allocated_interval.set_span(work_span);
         }
 
-        if scheduler.current_time + work_span >= scheduler.interval.end {
-            allocated_interval.end = scheduler.interval.end;
+        // a block running past the window ends with it, however much work is left
+        if allocated_interval.end > scheduler.interval.end {
+            allocated_interval.end = scheduler.interval.end.max(start);
         }
 
-        for plan_interval in self.plans.keys() {
-            if !allocated_interval.intercepts(plan_interval) {
+        // intervals are half open. a plan starting at or before the allocation start pushes the
+        // allocation past its end, one starting inside the allocation cuts it short there and one
+        // starting right at the allocation end leaves it alone
+        for plan_interval in blocked_intervals {
+            if plan_interval.end <= allocated_interval.start
+                || plan_interval.start >= allocated_interval.end
+            {
                 continue;
-            } else if allocated_interval.start >= plan_interval.start {
+            }
+            if plan_interval.start <= allocated_interval.start {
                 allocated_interval.move_to(plan_interval.end);
             } else {
-                allocated_interval.end = plan_interval.start
+                // the plan starts after the allocation here, so this never ends it before it starts
+                allocated_interval = Interval::new(allocated_interval.start, plan_interval.start);
             }
         }
 
@@ -56,11 +317,77 @@ impl TaskAllocatorWithPlans {
     }
 }
 
-#[derive(Into, Deref, DerefMut)]
-pub struct Plans(BTreeMap<Interval, String>);
+pub struct Plans {
+    plans: BTreeMap<Interval, String>,
+    // unique among all plans and renewed on every mutable access, so what is computed from the
+    // plans can tell it is stale
+    generation: u64,
+}
+
+fn next_generation() -> u64 {
+    static GENERATION: AtomicU64 = AtomicU64::new(0);
+    GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+impl Default for Plans {
+    fn default() -> Self {
+        BTreeMap::new().into()
+    }
+}
+
+impl Deref for Plans {
+    type Target = BTreeMap<Interval, String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.plans
+    }
+}
+
+impl DerefMut for Plans {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.generation = next_generation();
+        &mut self.plans
+    }
+}
+
+impl From<BTreeMap<Interval, String>> for Plans {
+    fn from(plans: BTreeMap<Interval, String>) -> Self {
+        Self {
+            plans,
+            generation: next_generation(),
+        }
+    }
+}
+
+impl From<Plans> for BTreeMap<Interval, String> {
+    fn from(plans: Plans) -> Self {
+        plans.plans
+    }
+}
 
 impl Plans {
-    pub fn remove_on_interval(&mut self, interval: &Interval) {
+    // inserts the plan, removing or splitting any existing plans it overlaps
+    pub fn insert_with_overriding(&mut self, interval: Interval, description: String) {
+        self.remove_interval(&interval);
+        self.insert(interval, description);
+    }
+
+    // inserts a plan of `span` starting at `time` on every day of `interval`, overriding others
+    pub fn insert_daily(
+        &mut self,
+        interval: &Interval,
+        time: Time,
+        span: Span,
+        description: &str,
+        tz: &TimeZone,
+    ) {
+        for occurrence in interval.daily(time, span, tz) {
+            self.insert_with_overriding(occurrence, description.into());
+        }
+    }
+
+    // frees `interval`, removing the plans it covers and splitting the ones it overlaps
+    pub fn remove_interval(&mut self, interval: &Interval) {
         let contained_intervals: Vec<_> = self
             .keys()
             .filter(|&k| interval.contains(k))
@@ -105,31 +432,94 @@ impl Plans {
     }
 }
 
+// named time ranges usable in place of `%R-%R` in plans
+pub fn default_time_aliases() -> IndexMap<String, String> {
+    IndexMap::from([
+        ("morning".into(), "09:00-12:00".into()),
+        ("afternoon".into(), "13:00-17:00".into()),
+        ("evening".into(), "18:00-22:00".into()),
+    ])
+}
+
 impl TryFrom<(&Interval, IndexMap<String, IndexMap<String, String>>)> for Plans {
     type Error = Box<dyn Error>;
 
     fn try_from(
         (interval, value): (&Interval, IndexMap<String, IndexMap<String, String>>),
     ) -> Result<Self, Self::Error> {
-        let mut plans = Plans(BTreeMap::new());
+        Plans::try_from((interval, &default_time_aliases(), value))
+    }
+}
+
+impl
+    TryFrom<(
+        &Interval,
+        &IndexMap<String, String>,
+        IndexMap<String, IndexMap<String, String>>,
+    )> for Plans
+{
+    type Error = Box<dyn Error>;
+
+    fn try_from(
+        (interval, aliases, value): (
+            &Interval,
+            &IndexMap<String, String>,
+            IndexMap<String, IndexMap<String, String>>,
+        ),
+    ) -> Result<Self, Self::Error> {
+        Plans::try_from((interval, aliases, None, value))
+    }
+}
+
+// `coordinates` are needed for plans starting or ending at `sunrise` or `sunset`
+impl
+    TryFrom<(
+        &Interval,
+        &IndexMap<String, String>,
+        Option<&Coordinates>,
+        IndexMap<String, IndexMap<String, String>>,
+    )> for Plans
+{
+    type Error = Box<dyn Error>;
+
+    fn try_from(
+        (interval, aliases, coordinates, value): (
+            &Interval,
+            &IndexMap<String, String>,
+            Option<&Coordinates>,
+            IndexMap<String, IndexMap<String, String>>,
+        ),
+    ) -> Result<Self, Self::Error> {
+        let mut plans = Plans::default();
         for (cron_part, day_plans) in value {
             let cron_string = "0 0 ".to_string() + &cron_part;
             let cron = Cron::new(&cron_string).parse()?;
 
+            // cron fields are matched against local dates, which croner sees as UTC midnights.
+            // starts a day early, so ranges running past midnight cover the first morning too
+            let [first_day, last_day] = [interval.start, interval.end]
+                .map(|timestamp| timestamp.to_zoned(TimeZone::system()).date());
+            let from = to_chrono(first_day.yesterday()?.to_zoned(TimeZone::UTC)?.timestamp());
+
             for (time, description) in day_plans {
-                for datetime in cron
-                    .iter_from(to_chrono(
-                        interval
-                            .start
-                            .to_zoned(TimeZone::system())
-                            .round(ZonedRound::new().smallest(Unit::Day).mode(RoundMode::Trunc))
-                            .unwrap()
-                            .timestamp(),
-                    ))
-                    .take_while(|dt| from_chrono(*dt) < interval.end)
+                for date in cron
+                    .iter_from(from)
+                    .map(|datetime| from_chrono(datetime).to_zoned(TimeZone::UTC).date())
+                    .take_while(|date| *date <= last_day)
                 {
-                    let date = from_chrono(datetime);
-                    let [start, end]: [&str; 2] = time
+                    // an optional timezone follows the time range, e.g.
+                    // `09:00-10:00 Europe/London`. the range itself may contain spaces, e.g.
+                    // `09:00 - 10:00`
+                    let (range, tz) = match time
+                        .trim()
+                        .rsplit_once(' ')
+                        .and_then(|(range, tz)| Some((range.trim(), TimeZone::get(tz).ok()?)))
+                    {
+                        Some((range, tz)) => (range, tz),
+                        None => (time.trim(), TimeZone::system()),
+                    };
+                    let range = aliases.get(range).map_or(range, |range| range.as_str());
+                    let [start_time, end_time]: [&str; 2] = range
                         .split('-')
                         .map(|v| v.trim())
                         .collect::<Vec<_>>()
@@ -141,29 +531,31 @@ impl TryFrom<(&Interval, IndexMap<String, IndexMap<String, String>>)> for Plans
                                 e
                             )
                         })?;
-                    let start =
-                        DateTime::strptime("%F %R", format!("{} {}", date.strftime("%F"), start))?
-                            .to_zoned(TimeZone::system())
-                            .unwrap()
-                            .timestamp();
-
-                    let end = if end.starts_with("24") {
-                        (&date.to_zoned(TimeZone::system()) + 1.day())
-                            .round(ZonedRound::new().smallest(Unit::Day).mode(RoundMode::Trunc))
-                            .unwrap()
-                            .timestamp()
-                    } else {
-                        DateTime::strptime("%F %R", format!("{} {}", date.strftime("%F"), end))?
-                            .to_zoned(TimeZone::system())
-                            .unwrap()
-                            .timestamp()
+                    // there is no sunrise or sunset during polar day or night
+                    let (Some(start), Some(mut end)) = (
+                        resolve_plan_time(start_time, date, &tz, coordinates)?,
+                        resolve_plan_time(end_time, date, &tz, coordinates)?,
+                    ) else {
+                        continue;
                     };
 
-                    let plan_interval = Interval::new(start, end);
+                    // ranges ending before they start, e.g. `22:00-06:00`, run into the next day
+                    if end <= start {
+                        let Some(next_end) =
+                            resolve_plan_time(end_time, date.tomorrow()?, &tz, coordinates)?
+                        else {
+                            continue;
+                        };
+                        end = next_end;
+                    }
+                    let Some(plan_interval) = Interval::new(start, end).clip_to(interval) else {
+                        continue;
+                    };
 
-                    plans.remove_on_interval(&plan_interval);
-                    if description != "null" {
-                        plans.insert(plan_interval, description.clone());
+                    if description == "null" {
+                        plans.remove_interval(&plan_interval);
+                    } else {
+                        plans.insert_with_overriding(plan_interval, description.clone());
                     }
                 }
             }
@@ -173,11 +565,42 @@ impl TryFrom<(&Interval, IndexMap<String, IndexMap<String, String>>)> for Plans
     }
 }
 
+// `%R`, `24:00` for the end of the day, `sunrise` or `sunset`
+fn resolve_plan_time(
+    time: &str,
+    date: Date,
+    tz: &TimeZone,
+    coordinates: Option<&Coordinates>,
+) -> Result<Option<Timestamp>, Box<dyn Error>> {
+    let solar = |event: fn(Date, &Coordinates) -> Option<Timestamp>| {
+        coordinates
+            .map(|coordinates| event(date, coordinates))
+            .ok_or_else(|| format!("Expected coordinates in the config to use {}", time))
+    };
+    Ok(match time {
+        "sunrise" => solar(solar::sunrise)?,
+        "sunset" => solar(solar::sunset)?,
+        time if time.starts_with("24") => Some(date.tomorrow()?.to_zoned(tz.clone())?.timestamp()),
+        time => Some(
+            date.to_datetime(Time::strptime("%R", time)?)
+                .to_zoned(tz.clone())?
+                .timestamp(),
+        ),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{interval::Interval, tests::get_test_scheduler};
-    use jiff::ToSpan;
+    use crate::{
+        interval::{Interval, TimeWindow},
+        tasks::Task,
+        tests::get_test_scheduler,
+    };
+    use jiff::{
+        civil::{date, time},
+        Timestamp, ToSpan,
+    };
 
     #[test]
     fn test_task_allocator() {
@@ -202,7 +625,9 @@ mod tests {
                     Interval::from_span(scheduler.current_time + 4.hours(), 1.hour()),
                     "".into(),
                 ),
-            ]),
+            ])
+            .into(),
+            ..Default::default()
         };
         let allocator = &scheduler.allocator;
 
@@ -241,4 +666,559 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_allowed_window_allocation() {
+        let mut scheduler = get_test_scheduler();
+        let task_idx = 5;
+        scheduler.tasks[task_idx].allowed_window =
+            Some(TimeWindow::new(time(16, 0, 0, 0), time(20, 0, 0, 0)));
+
+        scheduler.current_time = scheduler.interval.start + 15.hours();
+        let allocated_interval = scheduler.allocator.allocate(&scheduler, task_idx);
+
+        assert_eq!(
+            allocated_interval,
+            Interval::from_span(scheduler.interval.start + 16.hours(), 30.minutes())
+        );
+    }
+
+    #[test]
+    fn test_working_hours() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.allocator.plans.clear();
+        scheduler.allocator.granularity = 2.hours();
+        scheduler.allocator.working_hours =
+            Some(TimeWindow::new(time(9, 0, 0, 0), time(17, 0, 0, 0)));
+        let start = scheduler.interval.start;
+
+        // a block straddling the end of the day is cut short there
+        scheduler.current_time = start + 16.hours();
+        assert_eq!(
+            scheduler.allocator.allocate(&scheduler, 2),
+            Interval::from_span(start + 16.hours(), 1.hour())
+        );
+        // and one before the day starts is moved to its start
+        scheduler.current_time = start + 3.hours();
+        assert_eq!(
+            scheduler.allocator.allocate(&scheduler, 2),
+            Interval::from_span(start + 9.hours(), 2.hours())
+        );
+
+        scheduler.current_time = start;
+        scheduler.schedule();
+        let working_day = Interval::from_span(start + 9.hours(), 8.hours());
+        assert!(scheduler
+            .timeline()
+            .all(|(_, interval)| working_day.contains(interval)));
+        // the working day is filled, the time around it is left alone
+        let hours: f32 = scheduler
+            .timeline()
+            .map(|(_, interval)| interval.hours())
+            .sum();
+        assert_eq!(hours, 8.0);
+    }
+
+    #[test]
+    fn test_break_span() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.allocator.break_span = 30.minutes();
+        let start = scheduler.interval.start;
+
+        // the first block of the day right after the morning plan gets no break
+        let first = scheduler.allocator.allocate(&scheduler, 2);
+        assert_eq!(first, Interval::from_span(start + 9.hours(), 1.hour()));
+        scheduler.schedule_task(2, first.clone());
+        scheduler.current_time = first.end;
+
+        let second = scheduler.allocator.allocate(&scheduler, 2);
+        assert_eq!(second.start, first.end + 30.minutes());
+        assert_eq!(second.hours(), 1.0);
+
+        scheduler.schedule();
+        let timeline: Vec<_> = scheduler.timeline().map(|(_, interval)| interval).collect();
+        for pair in timeline.windows(2) {
+            let gap = pair[1].start.duration_since(pair[0].end);
+            assert!(gap.as_secs() >= 30 * 60, "{:?}", pair);
+        }
+
+        // the break after the last block of a day does not carry over into the next one
+        let mut scheduler = get_test_scheduler();
+        scheduler.allocator.plans.clear();
+        scheduler.allocator.break_span = 30.minutes();
+        scheduler.interval = Interval::from_span(start, 48.hours());
+        scheduler.schedule_task(2, Interval::from_span(start + 23.hours(), 1.hour()));
+        scheduler.current_time = start + 24.hours();
+        assert_eq!(
+            scheduler.allocator.allocate(&scheduler, 3).start,
+            start + 24.hours()
+        );
+    }
+
+    #[test]
+    fn test_min_notice() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.allocator.plans.clear();
+        scheduler.allocator.min_notice = 1.hour();
+
+        scheduler.schedule();
+
+        let notice_end = scheduler.interval.start + 1.hour();
+        assert!(scheduler
+            .iter()
+            .flatten()
+            .all(|interval| interval.start >= notice_end));
+        assert!(scheduler
+            .iter()
+            .flatten()
+            .any(|interval| interval.start == notice_end));
+    }
+
+    #[test]
+    fn test_plan_before_current_time() {
+        let mut scheduler = get_test_scheduler();
+        // the 00:00-09:00 plan is already underway, and so is the 13:00-15:00 one
+        for current_time in [
+            "2025-03-05T08:30Z",
+            "2025-03-05T12:30Z",
+            "2025-03-05T14:59Z",
+        ] {
+            scheduler.current_time = current_time.parse().unwrap();
+            for task_idx in 0..scheduler.tasks.len() {
+                let allocated_interval = scheduler.allocator.allocate(&scheduler, task_idx);
+                assert!(allocated_interval.start <= allocated_interval.end);
+                assert!(allocated_interval.start >= scheduler.current_time);
+            }
+        }
+        // negative spans are caught in debug builds
+        assert!(
+            !cfg!(debug_assertions)
+                || std::panic::catch_unwind(|| {
+                    Interval::from_span(Timestamp::UNIX_EPOCH, (-1).hour())
+                })
+                .is_err()
+        );
+        assert_eq!(
+            Interval::normalized(Timestamp::UNIX_EPOCH + 1.hour(), Timestamp::UNIX_EPOCH),
+            Interval::from_span(Timestamp::UNIX_EPOCH, 1.hour())
+        );
+    }
+
+    #[test]
+    fn test_min_session() {
+        let mut scheduler = get_test_scheduler();
+        // 20 minutes are left before the 13:00-15:00 plan
+        scheduler.current_time = "2025-03-05T12:40Z".parse().unwrap();
+        let gap = Interval::from_span(scheduler.current_time, 20.minutes());
+        assert_eq!(scheduler.allocator.allocate(&scheduler, 3), gap);
+
+        scheduler.allocator.min_session = 30.minutes();
+        assert_eq!(
+            scheduler.allocator.allocate(&scheduler, 3),
+            Interval::from_span("2025-03-05T15:00Z".parse().unwrap(), 1.hour())
+        );
+
+        // the last 10 minutes of a task still fit
+        scheduler.tasks[3].volume = 1.0 / 6.0;
+        assert_eq!(
+            scheduler.allocator.allocate(&scheduler, 3),
+            Interval::from_span(scheduler.current_time, 10.minutes())
+        );
+    }
+
+    #[test]
+    fn test_plan_boundaries() {
+        let mut scheduler = get_test_scheduler();
+        let allocate_at = |scheduler: &mut Scheduler, current_time: &str| {
+            scheduler.current_time = current_time.parse().unwrap();
+            scheduler.allocator.allocate(scheduler, 3)
+        };
+
+        // the 13:00-15:00 plan starts right at the current time
+        assert_eq!(
+            allocate_at(&mut scheduler, "2025-03-05T13:00Z"),
+            Interval::from_span("2025-03-05T15:00Z".parse().unwrap(), 1.hour())
+        );
+        // it starts in the middle of the allocation
+        assert_eq!(
+            allocate_at(&mut scheduler, "2025-03-05T12:30Z"),
+            Interval::from_span("2025-03-05T12:30Z".parse().unwrap(), 30.minutes())
+        );
+        // it starts right at the end of the allocation
+        assert_eq!(
+            allocate_at(&mut scheduler, "2025-03-05T12:00Z"),
+            Interval::from_span("2025-03-05T12:00Z".parse().unwrap(), 1.hour())
+        );
+    }
+
+    #[test]
+    fn test_large_volume() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.tasks[3].volume = 1000.0;
+        scheduler.current_time = "2025-03-05T15:00Z".parse().unwrap();
+        assert_eq!(
+            scheduler.allocator.allocate(&scheduler, 3),
+            Interval::from_span(scheduler.current_time, 1.hour())
+        );
+
+        // a block long enough for the whole task still ends with the window
+        scheduler.allocator.granularity = 2000.hours();
+        scheduler.allocator.plans.clear();
+        assert_eq!(
+            scheduler.allocator.allocate(&scheduler, 3),
+            Interval::new(scheduler.current_time, scheduler.interval.end)
+        );
+    }
+
+    #[test]
+    fn test_daily_lunch() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.interval = Interval::from_span(scheduler.interval.start, 72.hours());
+        scheduler.allocator.plans.insert_daily(
+            &scheduler.interval,
+            time(12, 30, 0, 0),
+            1.hour(),
+            "Lunch",
+            &TimeZone::UTC,
+        );
+
+        for day in 0..3 {
+            let lunch = Interval::from_span(
+                scheduler.interval.start + (day * 24).hours().minutes(12 * 60 + 30),
+                1.hour(),
+            );
+            assert_eq!(scheduler.allocator.plans.get(&lunch).unwrap(), "Lunch");
+
+            scheduler.current_time = lunch.start - 30.minutes();
+            let allocated_interval = scheduler.allocator.allocate(&scheduler, 3);
+            assert_eq!(allocated_interval.end, lunch.start);
+        }
+    }
+
+    #[test]
+    fn test_daily_capacity() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.interval = Interval::from_span(scheduler.interval.start, 48.hours());
+        // working hours of 09:00-17:00, with an hour long meeting on the first day
+        scheduler.allocator.plans = Plans::default();
+        scheduler.allocator.plans.insert_daily(
+            &scheduler.interval,
+            time(0, 0, 0, 0),
+            9.hours(),
+            "",
+            &TimeZone::UTC,
+        );
+        scheduler.allocator.plans.insert_daily(
+            &scheduler.interval,
+            time(17, 0, 0, 0),
+            7.hours(),
+            "",
+            &TimeZone::UTC,
+        );
+        scheduler.allocator.plans.insert_with_overriding(
+            Interval::from_span(scheduler.interval.start + 10.hours(), 1.hour()),
+            "Meeting".into(),
+        );
+
+        let first_day = scheduler.get_day(scheduler.interval.start);
+        let second_day = scheduler.get_day(first_day.end);
+        let capacity_on =
+            |scheduler: &Scheduler, day: &Interval| scheduler.allocator.capacity_on(scheduler, day);
+        assert_eq!(capacity_on(&scheduler, &first_day), 7.0);
+        assert_eq!(capacity_on(&scheduler, &second_day), 8.0);
+        assert_eq!(
+            capacity_on(&scheduler, &scheduler.get_day(second_day.end)),
+            0.0
+        );
+
+        // plans changed directly count right away
+        scheduler.allocator.plans.insert(
+            Interval::from_span(second_day.start + 9.hours(), 8.hours()),
+            "Offsite".into(),
+        );
+        assert!(!scheduler.allocator.has_capacity_on(&scheduler, &second_day));
+
+        // the cached capacity of a day with a plan and a working hours window is the computed one
+        scheduler.allocator.working_hours =
+            Some(TimeWindow::new(time(10, 0, 0, 0), time(16, 0, 0, 0)));
+        scheduler.allocator.reset();
+        assert_eq!(capacity_on(&scheduler, &first_day), 5.0);
+        assert_eq!(
+            capacity_on(&scheduler, &first_day),
+            scheduler
+                .allocator
+                .compute_capacity_on(&scheduler, &first_day)
+        );
+        assert_eq!(
+            scheduler
+                .allocator
+                .daily_capacity
+                .days
+                .borrow()
+                .keys()
+                .collect::<Vec<_>>(),
+            [&date(2025, 3, 5)]
+        );
+    }
+
+    #[test]
+    fn test_plan_timezones() {
+        let interval = Interval::from_span("2025-03-12T00:00Z".parse().unwrap(), 24.hours());
+        let plans = IndexMap::from([(
+            "12 3 *".to_string(),
+            IndexMap::from([
+                (
+                    "09:00-10:00 America/New_York".to_string(),
+                    "Flight".to_string(),
+                ),
+                (
+                    "14:00 - 15:00 Europe/London".to_string(),
+                    "Meeting".to_string(),
+                ),
+                ("16:00 - 17:00".to_string(), "Standup".to_string()),
+            ]),
+        )]);
+
+        let plans = Plans::try_from((&interval, plans)).unwrap();
+
+        let standup = date(2025, 3, 12)
+            .at(16, 0, 0, 0)
+            .to_zoned(TimeZone::system())
+            .unwrap()
+            .timestamp();
+        assert_eq!(
+            *plans,
+            BTreeMap::from([
+                (
+                    Interval::from_span("2025-03-12T13:00Z".parse().unwrap(), 1.hour()),
+                    "Flight".to_string()
+                ),
+                (
+                    Interval::from_span("2025-03-12T14:00Z".parse().unwrap(), 1.hour()),
+                    "Meeting".to_string()
+                ),
+                (
+                    Interval::from_span(standup, 1.hour()),
+                    "Standup".to_string()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_sunset_plans() {
+        let interval = Interval::from_span("2025-06-21T00:00Z".parse().unwrap(), 24.hours());
+        let london = Coordinates {
+            latitude: 51.5074,
+            longitude: -0.1278,
+        };
+        let value = IndexMap::from([(
+            "* * *".to_string(),
+            IndexMap::from([("sunset-22:00 UTC".to_string(), "Walk".to_string())]),
+        )]);
+
+        let plans = Plans::try_from((
+            &interval,
+            &default_time_aliases(),
+            Some(&london),
+            value.clone(),
+        ))
+        .unwrap();
+        let (walk, _) = plans.iter().next().unwrap();
+        // sunset is at 21:21 London summer time
+        assert!(walk.approx_eq(
+            &Interval::new(
+                "2025-06-21T20:21Z".parse().unwrap(),
+                "2025-06-21T22:00Z".parse().unwrap()
+            ),
+            3.minutes()
+        ));
+
+        let err = Plans::try_from((&interval, value)).err().unwrap();
+        assert!(err.to_string().contains("coordinates"), "{}", err);
+    }
+
+    #[test]
+    fn test_plans_clipped_to_window() {
+        let interval = Interval::from_span("2025-03-05T00:00Z".parse().unwrap(), 47.hours());
+        let plans = IndexMap::from([(
+            "* * *".to_string(),
+            IndexMap::from([("22:00-24:00 UTC".to_string(), "Late night".to_string())]),
+        )]);
+
+        let plans = Plans::try_from((&interval, plans)).unwrap();
+
+        assert_eq!(
+            plans.keys().cloned().collect::<Vec<_>>(),
+            vec![
+                Interval::from_span("2025-03-05T22:00Z".parse().unwrap(), 2.hours()),
+                Interval::from_span("2025-03-06T22:00Z".parse().unwrap(), 1.hour()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overnight_plans() {
+        let interval = Interval::from_span("2025-03-05T00:00Z".parse().unwrap(), 48.hours());
+        let plans = IndexMap::from([(
+            "* * *".to_string(),
+            IndexMap::from([("22:00-06:00 UTC".to_string(), "Sleep".to_string())]),
+        )]);
+
+        let plans = Plans::try_from((&interval, plans)).unwrap();
+
+        // the night before the interval still covers its first morning
+        assert_eq!(
+            plans.keys().cloned().collect::<Vec<_>>(),
+            vec![
+                Interval::from_span("2025-03-05T00:00Z".parse().unwrap(), 6.hours()),
+                Interval::from_span("2025-03-05T22:00Z".parse().unwrap(), 8.hours()),
+                Interval::from_span("2025-03-06T22:00Z".parse().unwrap(), 2.hours()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove_interval() {
+        let start: Timestamp = "2025-03-05T09:00Z".parse().unwrap();
+        let mut plans = Plans::from(BTreeMap::from([
+            (Interval::from_span(start, 8.hours()), "Work".to_string()),
+            (
+                Interval::from_span(start + 9.hours(), 2.hours()),
+                "Gym".to_string(),
+            ),
+        ]));
+
+        plans.remove_interval(&Interval::from_span(start + 5.hours(), 1.hour()));
+
+        assert_eq!(
+            *plans,
+            BTreeMap::from([
+                (Interval::from_span(start, 5.hours()), "Work".to_string()),
+                (
+                    Interval::from_span(start + 6.hours(), 2.hours()),
+                    "Work".to_string()
+                ),
+                (
+                    Interval::from_span(start + 9.hours(), 2.hours()),
+                    "Gym".to_string()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_insert_with_overriding() {
+        let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+        let hours = |from: i64, to: i64| Interval::new(start + from.hours(), start + to.hours());
+        // a new plan over the existing `2h - 6h` one, and the existing plan's parts that are left
+        for (new, old_left) in [
+            (hours(2, 6), vec![]),
+            (hours(1, 7), vec![]),
+            (hours(3, 5), vec![hours(2, 3), hours(5, 6)]),
+            (hours(2, 4), vec![hours(4, 6)]),
+            (hours(4, 6), vec![hours(2, 4)]),
+            (hours(1, 3), vec![hours(3, 6)]),
+            (hours(5, 7), vec![hours(2, 5)]),
+            (hours(0, 2), vec![hours(2, 6)]),
+            (hours(6, 8), vec![hours(2, 6)]),
+        ] {
+            let mut plans = Plans::from(BTreeMap::from([(hours(2, 6), "Old".to_string())]));
+            plans.insert_with_overriding(new.clone(), "New".to_string());
+
+            let mut expected: BTreeMap<_, _> = old_left
+                .into_iter()
+                .map(|interval| (interval, "Old".to_string()))
+                .collect();
+            expected.insert(new.clone(), "New".to_string());
+            assert_eq!(*plans, expected, "{:?}", new);
+        }
+    }
+
+    #[test]
+    fn test_time_aliases() {
+        let mut scheduler = get_test_scheduler();
+        let mut aliases = default_time_aliases();
+        aliases.insert("afternoon".into(), "12:00-16:00".into());
+
+        let plans = IndexMap::from([(
+            "* * *".to_string(),
+            IndexMap::from([("afternoon UTC".to_string(), "Meeting".to_string())]),
+        )]);
+        scheduler.allocator.plans =
+            Plans::try_from((&scheduler.interval, &aliases, plans)).unwrap();
+
+        let afternoon = Interval::from_span(scheduler.interval.start + 12.hours(), 4.hours());
+        assert_eq!(
+            scheduler.allocator.plans.get(&afternoon).unwrap(),
+            "Meeting"
+        );
+
+        scheduler.current_time = afternoon.start;
+        let allocated_interval = scheduler.allocator.allocate(&scheduler, 3);
+        assert_eq!(allocated_interval.start, afternoon.end);
+    }
+
+    #[test]
+    fn test_fractional_volume() {
+        let mut scheduler = get_test_scheduler();
+        let task_idx = 2;
+        scheduler.allocator.granularity = 2.hours();
+        // 1.5 hours give or take float error, which used to truncate to 89:59
+        scheduler.tasks[task_idx].volume = 2.1 - 0.6;
+
+        scheduler.current_time = scheduler.interval.start + 9.hours();
+        let allocated_interval = scheduler.allocator.allocate(&scheduler, task_idx);
+
+        assert_eq!(
+            allocated_interval,
+            Interval::from_span(scheduler.current_time, 90.minutes())
+        );
+    }
+
+    #[test]
+    fn test_task_granularity() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.tasks[2] =
+            Task::try_from("Deep work / 2025-03-05 / 6h / 0% / gran=2h".to_string()).unwrap();
+        scheduler.current_time = scheduler.interval.start + 9.hours();
+
+        assert_eq!(
+            scheduler.allocator.allocate(&scheduler, 2),
+            Interval::from_span(scheduler.current_time, 2.hours())
+        );
+        assert_eq!(
+            scheduler.allocator.allocate(&scheduler, 3),
+            Interval::from_span(scheduler.current_time, 1.hour())
+        );
+    }
+
+    #[test]
+    fn test_multiple_granularities() {
+        let mut scheduler = get_test_scheduler();
+        let task_idx = 3;
+        scheduler.allocator.granularity = 2.hours();
+        scheduler.allocator.granularities = vec![30.minutes()];
+
+        scheduler.current_time = scheduler.interval.start + 9.hours();
+        let allocated_interval = scheduler.allocator.allocate(&scheduler, task_idx);
+        assert_eq!(
+            allocated_interval,
+            Interval::from_span(scheduler.current_time, 2.hours())
+        );
+
+        scheduler.current_time = scheduler.interval.start + 12.hours().minutes(15);
+        let allocated_interval = scheduler.allocator.allocate(&scheduler, task_idx);
+        assert_eq!(
+            allocated_interval,
+            Interval::from_span(scheduler.current_time, 30.minutes())
+        );
+
+        scheduler.current_time = scheduler.interval.start + 12.hours().minutes(45);
+        let allocated_interval = scheduler.allocator.allocate(&scheduler, task_idx);
+        assert_eq!(
+            allocated_interval,
+            Interval::from_span(scheduler.current_time, 15.minutes())
+        );
+    }
 }
@@ -0,0 +1,23 @@
+use jiff::Timestamp;
+
+// source of the current time for `now` in configs, so it can be fixed in tests
+pub trait Clock {
+    fn now(&self) -> Timestamp;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+// always returns the same time
+pub struct FixedClock(pub Timestamp);
+
+impl Clock for FixedClock {
+    fn now(&self) -> Timestamp {
+        self.0
+    }
+}
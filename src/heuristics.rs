@@ -1,14 +1,39 @@
-use crate::{interval::Interval, tasks::TaskIdx, Scheduler};
-use jiff::{tz::TimeZone, Unit};
+use crate::{allocators::PlanKind, interval::Interval, tasks::TaskIdx, Scheduler};
+use jiff::{Timestamp, Unit};
+use std::any::TypeId;
 
-pub type Heuristic = fn(&Scheduler, TaskIdx) -> f32;
+pub type Heuristic = Box<dyn Fn(&Scheduler, TaskIdx) -> f32>;
+
+// what a task being favoured by the heuristic says about its placement, for the heuristics that
+// explain one on their own. matched by the function itself, so any name can be given to them
+pub fn placement_phrase<F: 'static>(_: &F) -> Option<&'static str> {
+    fn id<T: 'static>(_: T) -> TypeId {
+        TypeId::of::<T>()
+    }
+    let phrases = [
+        (id(deadline), "placed here to meet its deadline"),
+        (id(lead_deadline), "placed here to meet its deadline"),
+        (id(priority), "higher priority than the other tasks"),
+        (id(volume), "the most work left"),
+        (id(locality), "batched with the previous task"),
+        (id(context), "its context is available now"),
+    ];
+    phrases
+        .into_iter()
+        .find(|(id, _)| *id == TypeId::of::<F>())
+        .map(|(_, phrase)| phrase)
+}
 
 // if the task is not dependent on any other task or other tasks are past the deadline,
-// it will be 1.0, 0.0 otherwise
+// it will be 1.0, 0.0 otherwise. heuristics are neutral for out of range task indices
 pub fn dependency(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
-    let task = &scheduler.tasks[task_idx];
+    let Some(task) = scheduler.task(task_idx) else {
+        return 1.0;
+    };
     let condition = task.dependencies.iter().all(|&dependency_idx| {
-        let dependency = &scheduler.tasks[dependency_idx];
+        let Some(dependency) = scheduler.task(dependency_idx) else {
+            return true;
+        };
         dependency.deadline <= scheduler.current_time
             || dependency.volume - scheduler.get_total_task_hours(dependency_idx) <= f32::EPSILON
     });
@@ -19,29 +44,39 @@ pub fn dependency(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
     }
 }
 
+// 2.0 once all of the task's soft dependencies are done, 1.0 otherwise or without any
+pub fn soft_dependency(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
+    let Some(task) = scheduler.task(task_idx) else {
+        return 1.0;
+    };
+    let done = task.soft_dependencies.iter().all(|&dependency_idx| {
+        let Some(dependency) = scheduler.task(dependency_idx) else {
+            return true;
+        };
+        dependency.volume - scheduler.get_total_task_hours(dependency_idx) <= f32::EPSILON
+    });
+    if !task.soft_dependencies.is_empty() && done {
+        2.0
+    } else {
+        1.0
+    }
+}
+
 // proportional to priority of the task. e.g. priority 2.0 means that task heuristic score will be multiplied by 2.0
+// priorities above `Scheduler::priority_cap` count as the cap
 pub fn priority(schedule: &Scheduler, task_idx: TaskIdx) -> f32 {
-    let task = &schedule.tasks[task_idx];
-    task.priority
+    let cap = schedule.priority_cap.unwrap_or(f32::INFINITY);
+    schedule
+        .task(task_idx)
+        .map_or(1.0, |task| task.priority.min(cap))
 }
 
 // inversely proportional to the amount of hours I can work on the task until the deadline
 pub fn deadline(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
-    let task = &scheduler.tasks[task_idx];
-    let total = task.deadline - scheduler.current_time;
-    let total_hours = total
-        .total((
-            Unit::Hour,
-            &scheduler.current_time.to_zoned(TimeZone::system()),
-        ))
-        .expect("Failed to convert total to hours") as f32;
-
-    let planned_hours = scheduler.get_planned_hours(Interval::new(
-        scheduler.current_time,
-        scheduler.current_time + total,
-    ));
-
-    let working_hours = total_hours - planned_hours;
+    let Some(task) = scheduler.task(task_idx) else {
+        return 1.0;
+    };
+    let working_hours = working_hours(scheduler, task.deadline);
     if working_hours <= 0.0 {
         return 0.0;
     }
@@ -49,12 +84,283 @@ pub fn deadline(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
     1.0 / working_hours
 }
 
+// a replacement for `deadline` that measures the urgency of tasks with an ideal lead against
+// finishing that long before the deadline. once into the lead the task is as urgent as one block
+// of work left, but it can still be scheduled up to the real deadline
+pub fn lead_deadline(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
+    let Some(task) = scheduler.task(task_idx) else {
+        return 1.0;
+    };
+    let Some(ideal_lead) = task.ideal_lead else {
+        return deadline(scheduler, task_idx);
+    };
+    if working_hours(scheduler, task.deadline) <= 0.0 {
+        return 0.0;
+    }
+
+    let ideal_deadline = task
+        .deadline
+        .checked_sub(ideal_lead)
+        .unwrap_or(Timestamp::MIN);
+    let block_hours =
+        Interval::from_span(scheduler.current_time, scheduler.allocator.granularity).hours();
+    1.0 / working_hours(scheduler, ideal_deadline).max(block_hours)
+}
+
+// a replacement for registering both `priority` and `deadline`. `priority / working_hours` alone
+// would be the same as their product, so the deadline is softened to the square root of the
+// working hours, letting a high priority outweigh a somewhat tighter deadline
+pub fn weighted_urgency() -> Heuristic {
+    Box::new(|scheduler, task_idx| {
+        let Some(task) = scheduler.task(task_idx) else {
+            return 1.0;
+        };
+        let working_hours = working_hours(scheduler, task.deadline);
+        if working_hours <= 0.0 {
+            return 0.0;
+        }
+
+        priority(scheduler, task_idx) / working_hours.sqrt()
+    })
+}
+
+// hours until `deadline` that are within the working hours and not taken by hard plans
+fn working_hours(scheduler: &Scheduler, deadline: Timestamp) -> f32 {
+    if deadline <= scheduler.current_time {
+        return 0.0;
+    }
+    let before_deadline = Interval::new(scheduler.current_time, deadline);
+    scheduler.get_free_hours_excluding(before_deadline, &[PlanKind::Soft])
+}
+
+// remaining hours of work divided by the hours available to work on the task until the deadline,
+// i.e. the pace required to finish the task in time. unlike `deadline`, soft plans are not counted
+// as available
+pub fn volume_weighted_by_deadline() -> Heuristic {
+    Box::new(|scheduler, task_idx| {
+        let Some(task) = scheduler.task(task_idx) else {
+            return 1.0;
+        };
+        if task.deadline <= scheduler.current_time {
+            return 0.0;
+        }
+
+        let until_deadline = Interval::new(scheduler.current_time, task.deadline);
+        let working_hours = scheduler.get_free_hours_excluding(until_deadline, &[]);
+        if working_hours <= 0.0 {
+            return 0.0;
+        }
+
+        (task.volume - scheduler.get_total_task_hours(task_idx)) / working_hours
+    })
+}
+
+// proportional to the number of unfinished tasks, including this one, due on the same day as the
+// task, so that work on crowded deadline days starts early
+pub fn deadline_spread() -> Heuristic {
+    Box::new(|scheduler, task_idx| {
+        let Some(task) = scheduler.task(task_idx) else {
+            return 1.0;
+        };
+        let deadline_day = task.deadline.to_zoned(scheduler.tz.clone()).date();
+
+        scheduler
+            .tasks
+            .iter()
+            .enumerate()
+            .filter(|(idx, other)| {
+                other.volume - scheduler.get_total_task_hours(*idx) > f32::EPSILON
+                    && other.deadline.to_zoned(scheduler.tz.clone()).date() == deadline_day
+            })
+            .count()
+            .max(1) as f32
+    })
+}
+
+// 1.0 on the deadline, reduced by `penalty_per_day` for each day before it, never below 0.0
+pub fn just_in_time(penalty_per_day: f32) -> Heuristic {
+    Box::new(move |scheduler, task_idx| {
+        let Some(task) = scheduler.task(task_idx) else {
+            return 1.0;
+        };
+        let hours_early = (task.deadline - scheduler.current_time)
+            .total(Unit::Hour)
+            .unwrap_or_default()
+            .max(0.0) as f32;
+        (1.0 - penalty_per_day * hours_early / 24.0).max(0.0)
+    })
+}
+
 // proportional to volume units which are hours of work needed to finish the task
 pub fn volume(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
-    let task = &scheduler.tasks[task_idx];
+    let Some(task) = scheduler.task(task_idx) else {
+        return 1.0;
+    };
     task.volume - scheduler.get_total_task_hours(task_idx)
 }
 
+// how `volume_saturating` flattens large remaining volumes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VolumeShape {
+    // remaining hours, but at most the cap
+    Capped(f32),
+    // 1 + ln(1 + remaining hours), 0.0 once the task is done
+    Logarithmic,
+}
+
+// like `volume`, with diminishing returns so that big tasks do not overwhelm small ones
+pub fn volume_saturating(shape: VolumeShape) -> Heuristic {
+    Box::new(move |scheduler, task_idx| {
+        if scheduler.task(task_idx).is_none() {
+            return 1.0;
+        }
+        let remaining = volume(scheduler, task_idx);
+        if remaining <= 0.0 {
+            return 0.0;
+        }
+        match shape {
+            VolumeShape::Capped(cap) => remaining.min(cap),
+            VolumeShape::Logarithmic => 1.0 + remaining.ln_1p(),
+        }
+    })
+}
+
+// 0.0 before the milestone the task waits on, 1.0 otherwise
+pub fn milestone(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
+    match scheduler.task(task_idx).and_then(|task| task.not_before) {
+        Some(not_before) if scheduler.current_time < not_before => 0.0,
+        _ => 1.0,
+    }
+}
+
+// 0.0 outside of the task's allowed window, 2.0 inside of its preferred window, 1.0 otherwise
+pub fn time_window(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
+    let Some(task) = scheduler.task(task_idx) else {
+        return 1.0;
+    };
+    if let Some(allowed_window) = &task.allowed_window {
+        if !allowed_window.contains(scheduler.current_time, &scheduler.tz) {
+            return 0.0;
+        }
+    }
+    match &task.preferred_window {
+        Some(preferred_window)
+            if preferred_window.contains(scheduler.current_time, &scheduler.tz) =>
+        {
+            2.0
+        }
+        _ => 1.0,
+    }
+}
+
+// 0.0 once the task has used up its weekly budget in the current week, 1.0 otherwise
+pub fn weekly_budget(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
+    let Some(task) = scheduler.task(task_idx) else {
+        return 1.0;
+    };
+    let Some(budget) = task.weekly_budget else {
+        return 1.0;
+    };
+
+    let week = scheduler.get_week(scheduler.current_time);
+    if scheduler.get_task_hours_on(task_idx, &week) >= budget {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+// 0.0 once the task has used up its daily volume on the current day, or on days without any free
+// time, 1.0 otherwise
+pub fn daily_volume(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
+    let Some(task) = scheduler.task(task_idx) else {
+        return 1.0;
+    };
+    let Some(daily_volume) = task.daily_volume else {
+        return 1.0;
+    };
+
+    let day = scheduler.get_day(scheduler.current_time);
+    if !scheduler.allocator.has_capacity_on(scheduler, &day)
+        || scheduler.get_task_hours_on(task_idx, &day) >= daily_volume
+    {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+// 0.0 for tasks not started on the current day once `max_tasks_per_day` different tasks are
+// scheduled on it, 1.0 otherwise
+pub fn max_tasks_per_day(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
+    let Some(max_tasks) = scheduler.max_tasks_per_day else {
+        return 1.0;
+    };
+
+    let day = scheduler.get_day(scheduler.current_time);
+    let mut day_tasks: Vec<_> = scheduler
+        .timeline()
+        .filter(|(_, interval)| interval.intercepts(&day))
+        .map(|(idx, _)| idx)
+        .collect();
+    day_tasks.sort();
+    day_tasks.dedup();
+
+    if day_tasks.len() >= max_tasks && !day_tasks.contains(&task_idx) {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+// value of the task per remaining hour of work, favouring high-payoff quick wins. tasks without a
+// value are neutral
+pub fn value_density() -> Heuristic {
+    Box::new(|scheduler, task_idx| {
+        let Some(task) = scheduler.task(task_idx) else {
+            return 1.0;
+        };
+        let Some(value) = task.value else {
+            return 1.0;
+        };
+
+        let remaining_hours = task.volume - scheduler.get_total_task_hours(task_idx);
+        if remaining_hours <= f32::EPSILON {
+            return 0.0;
+        }
+
+        value / remaining_hours
+    })
+}
+
+// 0.0 once another task of the task's exclusive group is complete, 1.0 otherwise
+pub fn exclusive_group(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
+    if scheduler.is_exclusive_sibling_complete(task_idx) {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+// 0.0 if the task needs a context that is not available at the current time, 1.0 otherwise
+pub fn context(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
+    let Some(required_context) = scheduler
+        .task(task_idx)
+        .and_then(|task| task.context.as_ref())
+    else {
+        return 1.0;
+    };
+
+    let is_available = scheduler.contexts.iter().any(|(interval, context)| {
+        context == required_context && interval.contains_timestamp(scheduler.current_time)
+    });
+    if is_available {
+        1.0
+    } else {
+        0.0
+    }
+}
+
 pub fn locality(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
     let Some(previous_task) = scheduler.get_last_task() else {
         return 1.0;
@@ -66,11 +372,56 @@ pub fn locality(scheduler: &Scheduler, task_idx: TaskIdx) -> f32 {
     }
 }
 
+// like `locality`, but also boosts tasks sharing a tag with the previous task by `boost`
+pub fn tag_locality(boost: f32) -> Heuristic {
+    Box::new(move |scheduler, task_idx| {
+        let Some(previous_task) = scheduler.get_last_task() else {
+            return 1.0;
+        };
+        if previous_task == task_idx {
+            return 4.0;
+        }
+
+        let (Some(previous_task), Some(task)) =
+            (scheduler.task(previous_task), scheduler.task(task_idx))
+        else {
+            return 1.0;
+        };
+        if task.tags.iter().any(|tag| previous_task.tags.contains(tag)) {
+            boost
+        } else {
+            1.0
+        }
+    })
+}
+
+// boosts tasks that got some but not all of their time by `boost`, so interrupted tasks are
+// picked up again before fresh ones are started
+pub fn prefer_started(boost: f32) -> Heuristic {
+    Box::new(move |scheduler, task_idx| {
+        let Some(task) = scheduler.task(task_idx) else {
+            return 1.0;
+        };
+        let scheduled_hours = scheduler.get_total_task_hours(task_idx);
+        if scheduled_hours > 0.0 && task.volume - scheduled_hours > f32::EPSILON {
+            boost
+        } else {
+            1.0
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{interval::Interval, tests::get_test_scheduler};
-    use jiff::ToSpan;
+    use crate::{
+        interval::{Interval, TimeWindow},
+        tests::get_test_scheduler,
+    };
+    use jiff::{
+        civil::{time, Weekday},
+        ToSpan,
+    };
 
     #[test]
     fn test_dependency_heuristic() {
@@ -102,6 +453,26 @@ mod tests {
         assert_eq!(score, 1.0);
     }
 
+    #[test]
+    fn test_placement_phrase() {
+        assert_eq!(
+            placement_phrase(&lead_deadline),
+            Some("placed here to meet its deadline")
+        );
+        assert_eq!(
+            placement_phrase(&locality),
+            Some("batched with the previous task")
+        );
+        assert_eq!(
+            placement_phrase(&context),
+            Some("its context is available now")
+        );
+        assert_eq!(placement_phrase(&dependency), None);
+        // boxed heuristics and closures explain nothing on their own
+        assert_eq!(placement_phrase(&just_in_time(0.5)), None);
+        assert_eq!(placement_phrase(&|_: &Scheduler, _: TaskIdx| 1.0), None);
+    }
+
     #[test]
     fn test_priority_heuristic() {
         let mut scheduler = get_test_scheduler();
@@ -129,6 +500,13 @@ mod tests {
         let score = deadline(&scheduler, task_idx);
         assert_eq!(score, 1.0 / 7.0);
 
+        // time outside of the working hours counts as planned
+        scheduler.allocator.working_hours =
+            Some(TimeWindow::new(time(10, 0, 0, 0), time(17, 0, 0, 0)));
+        let score = deadline(&scheduler, 0);
+        assert_eq!(score, 1.0 / 2.0);
+        scheduler.allocator.working_hours = None;
+
         let task_idx = 2;
         scheduler.current_time = scheduler.tasks[task_idx].deadline + 1.hour();
         let score = deadline(&scheduler, task_idx);
@@ -139,6 +517,24 @@ mod tests {
         assert_eq!(score, 0.0);
     }
 
+    #[test]
+    fn test_weighted_urgency_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        // 3 working hours left for the tight task and 7 for the loose one
+        let (tight_idx, loose_idx) = (0, 3);
+        scheduler.tasks[loose_idx].priority = 2.0;
+
+        let product = |idx| priority(&scheduler, idx) * deadline(&scheduler, idx);
+        assert!(product(tight_idx) > product(loose_idx));
+
+        let weighted_urgency = weighted_urgency();
+        assert!(weighted_urgency(&scheduler, loose_idx) > weighted_urgency(&scheduler, tight_idx));
+        assert_eq!(
+            weighted_urgency(&scheduler, tight_idx),
+            1.0 / 3.0_f32.sqrt()
+        );
+    }
+
     #[test]
     fn test_volume_heuristic() {
         let mut scheduler = get_test_scheduler();
@@ -159,4 +555,248 @@ mod tests {
         let score = volume(&scheduler, task_idx);
         assert_eq!(score, 0.5);
     }
+
+    #[test]
+    fn test_time_window_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        let task_idx = 2;
+        scheduler.tasks[task_idx].preferred_window =
+            Some(TimeWindow::new(time(9, 0, 0, 0), time(12, 0, 0, 0)));
+        scheduler.tasks[task_idx].allowed_window =
+            Some(TimeWindow::new(time(9, 0, 0, 0), time(20, 0, 0, 0)));
+
+        scheduler.current_time = scheduler.interval.start + 10.hours();
+        let preferred_score = time_window(&scheduler, task_idx);
+
+        scheduler.current_time = scheduler.interval.start + 18.hours();
+        let allowed_score = time_window(&scheduler, task_idx);
+
+        scheduler.current_time = scheduler.interval.start + 21.hours();
+        let outside_score = time_window(&scheduler, task_idx);
+
+        assert!(preferred_score > allowed_score);
+        assert!(allowed_score > 0.0);
+        assert_eq!(outside_score, 0.0);
+    }
+
+    #[test]
+    fn test_volume_weighted_by_deadline_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        let urgent_task_idx = 0;
+        let loose_task_idx = 3;
+        scheduler.tasks[loose_task_idx].deadline = scheduler.interval.start + 22.hours();
+        scheduler.current_time = scheduler.interval.start + 8.hours();
+
+        let pace = volume_weighted_by_deadline();
+        assert_eq!(pace(&scheduler, urgent_task_idx), 2.0 / 3.0);
+        assert_eq!(pace(&scheduler, loose_task_idx), 3.0 / 11.0);
+        assert!(pace(&scheduler, urgent_task_idx) > pace(&scheduler, loose_task_idx));
+
+        // a soft plan takes most of the time left for the urgent task, which the product of
+        // `volume` and `deadline` counts as available and so ranks the other task first
+        scheduler.current_time = scheduler.interval.start + 15.hours();
+        scheduler.tasks[urgent_task_idx].volume = 2.0;
+        scheduler.tasks[urgent_task_idx].deadline = scheduler.interval.start + 21.hours();
+        scheduler.tasks[loose_task_idx].volume = 2.5;
+        let gym = Interval::from_span(scheduler.interval.start + 16.hours(), 4.hours());
+        scheduler.allocator.plans.insert(gym, "Gym".into());
+        scheduler.allocator.soft_plans.insert("Gym".into());
+
+        let product = |task_idx| volume(&scheduler, task_idx) * deadline(&scheduler, task_idx);
+        assert!(product(urgent_task_idx) < product(loose_task_idx));
+        assert_eq!(pace(&scheduler, urgent_task_idx), 2.0 / 2.0);
+        assert_eq!(pace(&scheduler, loose_task_idx), 2.5 / 3.0);
+        assert!(pace(&scheduler, urgent_task_idx) > pace(&scheduler, loose_task_idx));
+    }
+
+    #[test]
+    fn test_weekly_budget_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        let task_idx = 2;
+        scheduler.tasks[task_idx].weekly_budget = Some(1.0);
+        scheduler.schedule_task(
+            task_idx,
+            Interval::from_span(scheduler.interval.start + 9.hours(), 1.hour()),
+        );
+
+        scheduler.current_time = scheduler.interval.start + 11.hours();
+        assert_eq!(weekly_budget(&scheduler, task_idx), 0.0);
+
+        scheduler.current_time = scheduler.interval.start + 34.hours();
+        assert_eq!(weekly_budget(&scheduler, task_idx), 0.0);
+
+        scheduler.week_start = Weekday::Thursday;
+        assert_eq!(weekly_budget(&scheduler, task_idx), 1.0);
+    }
+
+    #[test]
+    fn test_tag_locality_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.tasks[0].tags = vec!["writing".to_string()];
+        scheduler.tasks[1].tags = vec!["writing".to_string()];
+        scheduler.tasks[3].tags = vec!["coding".to_string()];
+        scheduler.schedule_task(
+            0,
+            Interval::from_span(scheduler.interval.start + 9.hours(), 1.hour()),
+        );
+
+        let tag_locality = tag_locality(2.0);
+        assert_eq!(tag_locality(&scheduler, 0), 4.0);
+        assert_eq!(tag_locality(&scheduler, 1), 2.0);
+        assert_eq!(tag_locality(&scheduler, 3), 1.0);
+    }
+
+    #[test]
+    fn test_prefer_started_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        let (started_idx, untouched_idx, finished_idx) = (3, 1, 0);
+        scheduler.schedule_task(
+            started_idx,
+            Interval::from_span(scheduler.interval.start + 9.hours(), 1.hour()),
+        );
+        scheduler.schedule_task(
+            finished_idx,
+            Interval::from_span(scheduler.interval.start + 10.hours(), 2.hours()),
+        );
+
+        let prefer_started = prefer_started(2.0);
+        let score = |idx| priority(&scheduler, idx) * prefer_started(&scheduler, idx);
+        assert!(score(started_idx) > score(untouched_idx));
+        assert_eq!(prefer_started(&scheduler, untouched_idx), 1.0);
+        assert_eq!(prefer_started(&scheduler, finished_idx), 1.0);
+    }
+
+    #[test]
+    fn test_value_density_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        let short_task_idx = 1;
+        let long_task_idx = 3;
+        scheduler.tasks[short_task_idx].value = Some(10.0);
+        scheduler.tasks[long_task_idx].value = Some(6.0);
+
+        let value_density = value_density();
+        assert_eq!(value_density(&scheduler, short_task_idx), 10.0);
+        assert_eq!(value_density(&scheduler, long_task_idx), 2.0);
+        assert!(
+            value_density(&scheduler, short_task_idx) > value_density(&scheduler, long_task_idx)
+        );
+    }
+
+    #[test]
+    fn test_deadline_spread_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        for task_idx in [0, 1, 3] {
+            scheduler.tasks[task_idx].deadline = "2025-03-07T12:00Z".parse().unwrap();
+        }
+        scheduler.tasks[2].deadline = "2025-03-08T12:00Z".parse().unwrap();
+
+        let deadline_spread = deadline_spread();
+        assert_eq!(deadline_spread(&scheduler, 0), 3.0);
+        assert_eq!(deadline_spread(&scheduler, 2), 1.0);
+
+        scheduler.schedule_task(
+            1,
+            Interval::from_span(scheduler.interval.start + 9.hours(), 1.hour()),
+        );
+        assert_eq!(deadline_spread(&scheduler, 0), 2.0);
+    }
+
+    #[test]
+    fn test_volume_saturating_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.tasks[0].volume = 4.0;
+        scheduler.tasks[1].volume = 40.0;
+        assert_eq!(volume(&scheduler, 1) / volume(&scheduler, 0), 10.0);
+
+        let capped = volume_saturating(VolumeShape::Capped(8.0));
+        assert_eq!(capped(&scheduler, 1) / capped(&scheduler, 0), 2.0);
+
+        let logarithmic = volume_saturating(VolumeShape::Logarithmic);
+        let ratio = logarithmic(&scheduler, 1) / logarithmic(&scheduler, 0);
+        assert!(ratio > 1.0 && ratio < 2.0, "{}", ratio);
+
+        // the "Empty task" has no work left
+        assert_eq!(logarithmic(&scheduler, 4), 0.0);
+        assert_eq!(capped(&scheduler, 4), 0.0);
+    }
+
+    #[test]
+    fn test_soft_dependency_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        let task_idx = 3;
+        scheduler.tasks[task_idx].dependencies = vec![];
+        scheduler.tasks[task_idx].soft_dependencies = vec![2];
+
+        assert_eq!(dependency(&scheduler, task_idx), 1.0);
+        assert_eq!(soft_dependency(&scheduler, task_idx), 1.0);
+
+        scheduler.schedule_task(
+            2,
+            Interval::from_span(scheduler.interval.start + 9.hours(), 3.hours()),
+        );
+        assert_eq!(soft_dependency(&scheduler, task_idx), 2.0);
+        assert_eq!(soft_dependency(&scheduler, 0), 1.0);
+    }
+
+    #[test]
+    fn test_max_tasks_per_day_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.max_tasks_per_day = Some(2);
+        scheduler.interval = Interval::from_span(scheduler.interval.start, 48.hours());
+        scheduler.tasks[3].dependencies.clear();
+        scheduler.tasks[3].deadline += 24.hours();
+        scheduler = scheduler.add_heuristic(max_tasks_per_day);
+
+        scheduler.schedule();
+
+        let first_day = scheduler.get_day(scheduler.interval.start);
+        let first_day_tasks: Vec<_> = (0..scheduler.tasks.len())
+            .filter(|&idx| scheduler.get_task_hours_on(idx, &first_day) > 0.0)
+            .collect();
+        assert_eq!(first_day_tasks.len(), 2);
+        assert!(!first_day_tasks.contains(&3));
+        assert!(scheduler[3]
+            .iter()
+            .all(|interval| interval.start >= first_day.end));
+        assert_eq!(scheduler.get_total_task_hours(3), 3.0);
+    }
+
+    #[test]
+    fn test_just_in_time_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        let task_idx = 0;
+        let just_in_time = just_in_time(0.05);
+
+        scheduler.current_time = scheduler.tasks[task_idx].deadline - (14 * 24).hours();
+        let two_weeks_early = just_in_time(&scheduler, task_idx);
+        scheduler.current_time = scheduler.tasks[task_idx].deadline - 48.hours();
+        let two_days_early = just_in_time(&scheduler, task_idx);
+        assert!(two_weeks_early < two_days_early);
+        assert!((two_days_early - 0.9).abs() < 1e-6);
+
+        scheduler.current_time = scheduler.tasks[task_idx].deadline - (60 * 24).hours();
+        assert_eq!(just_in_time(&scheduler, task_idx), 0.0);
+    }
+
+    #[test]
+    fn test_context_heuristic() {
+        let mut scheduler = get_test_scheduler();
+        let task_idx = 2;
+        scheduler.tasks[task_idx].context = Some("office".to_string());
+        scheduler.contexts.insert(
+            Interval::from_span(scheduler.interval.start + 9.hours(), 4.hours()),
+            "home".to_string(),
+        );
+        scheduler.contexts.insert(
+            Interval::from_span(scheduler.interval.start + 15.hours(), 3.hours()),
+            "office".to_string(),
+        );
+
+        scheduler.current_time = scheduler.interval.start + 10.hours();
+        assert_eq!(context(&scheduler, task_idx), 0.0);
+        assert_eq!(context(&scheduler, 3), 1.0);
+
+        scheduler.current_time = scheduler.interval.start + 16.hours();
+        assert_eq!(context(&scheduler, task_idx), 1.0);
+    }
 }
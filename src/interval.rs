@@ -1,21 +1,79 @@
-use jiff::{tz::TimeZone, Span, Timestamp, Unit};
+use jiff::{
+    civil::{Date, Time},
+    tz::TimeZone,
+    RoundMode, Span, Timestamp, TimestampRound, Unit, Zoned, ZonedRound,
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt};
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "SerializedInterval")]
 pub struct Interval {
     pub start: Timestamp,
     pub end: Timestamp,
 }
 
+// read intervals are truncated to whole seconds, the resolution the allocator works in, so ones
+// written with sub-second noise or another offset compare equal to the originals
+#[derive(Deserialize)]
+struct SerializedInterval {
+    start: Timestamp,
+    end: Timestamp,
+}
+
+impl TryFrom<SerializedInterval> for Interval {
+    type Error = Box<dyn Error>;
+
+    fn try_from(interval: SerializedInterval) -> Result<Self, Self::Error> {
+        let [start, end] = [interval.start, interval.end].map(|timestamp| {
+            timestamp
+                .round(
+                    TimestampRound::new()
+                        .smallest(Unit::Second)
+                        .mode(RoundMode::Trunc),
+                )
+                .unwrap_or(timestamp)
+        });
+        Interval::try_new(start, end)
+    }
+}
+
+// intervals never end before they start, which the constructors check in debug builds
 impl Interval {
     pub fn new(start: Timestamp, end: Timestamp) -> Self {
+        debug_assert!(
+            start <= end,
+            "Interval ends before it starts: {} - {}",
+            start,
+            end
+        );
         Self { start, end }
     }
 
-    pub fn from_span(start: Timestamp, span: Span) -> Self {
-        Self {
-            start,
-            end: start + span,
+    // `new` for bounds that come from outside, e.g. the config, checked in release builds too
+    pub fn try_new(start: Timestamp, end: Timestamp) -> Result<Self, Box<dyn Error>> {
+        if end < start {
+            return Err(format!(
+                "Expected the interval to end after it starts, got: {} - {}",
+                start, end
+            )
+            .into());
         }
+        Ok(Self { start, end })
+    }
+
+    // `new` without the check, for hot paths that already know the bounds are in order
+    pub fn new_unchecked(start: Timestamp, end: Timestamp) -> Self {
+        Self { start, end }
+    }
+
+    pub fn from_span(start: Timestamp, span: Span) -> Self {
+        Self::new(start, start + span)
+    }
+
+    // the interval between the two timestamps in whichever order they are given
+    pub fn normalized(a: Timestamp, b: Timestamp) -> Self {
+        Self::new(a.min(b), a.max(b))
     }
 
     pub fn move_to(&mut self, start: Timestamp) {
@@ -28,25 +86,404 @@ impl Interval {
     }
 
     pub fn hours(&self) -> f32 {
-        self.span()
-            .total((Unit::Hour, &self.start.to_zoned(TimeZone::system())))
-            .expect("Failed to convert span to hours") as f32
+        // timestamps have no calendar units, so there is no need for a time zone lookup here
+        (self.end.duration_since(self.start).as_secs_f64() / 3600.0) as f32
     }
 
     pub fn set_span(&mut self, span: Span) {
         self.end = self.start + span;
+        debug_assert!(self.start <= self.end, "Negative interval span: {}", span);
     }
 
+    // the intervals share some time, intervals that only touch do not
     pub fn intercepts(&self, other: &Self) -> bool {
         self.start < other.end && other.start < self.end
     }
 
+    // the intervals share some time, but neither contains the other
     pub fn partially_intercepts(&self, other: &Self) -> bool {
-        self.start < other.end && self.end > other.start && self.end < other.end
-            || self.end > other.end && self.start < other.end && self.start > other.start
+        self.intercepts(other) && !self.contains(other) && !other.contains(self)
     }
 
+    // `other` lies within the interval, which includes an identical interval
     pub fn contains(&self, other: &Self) -> bool {
         self.start <= other.start && self.end >= other.end
     }
+
+    // the part of the interval inside `bounds`, `None` if there is none
+    pub fn clip_to(&self, bounds: &Interval) -> Option<Interval> {
+        let (start, end) = (self.start.max(bounds.start), self.end.min(bounds.end));
+        (start < end).then(|| Interval::new_unchecked(start, end))
+    }
+
+    // the part both intervals share, `None` if they are disjoint or only touch. the same as
+    // `clip_to`, which reads better when neither interval is the bounds
+    pub fn overlap(&self, other: &Interval) -> Option<Interval> {
+        self.clip_to(other)
+    }
+
+    // both bounds are at most `tolerance` apart
+    pub fn approx_eq(&self, other: &Self, tolerance: Span) -> bool {
+        let tolerance = tolerance.abs();
+        [(self.start, other.start), (self.end, other.end)]
+            .iter()
+            .all(|(a, b)| (*a - *b).abs().compare(tolerance).is_ok_and(|o| o.is_le()))
+    }
+
+    // half-open, the start is inside the interval and the end is not
+    pub fn contains_timestamp(&self, timestamp: Timestamp) -> bool {
+        self.start <= timestamp && timestamp < self.end
+    }
+
+    // parts of the interval not covered by any of `others`
+    pub fn difference<'a>(&self, others: impl IntoIterator<Item = &'a Interval>) -> Vec<Interval> {
+        let mut others: Vec<_> = others.into_iter().filter(|o| o.intercepts(self)).collect();
+        others.sort();
+
+        let mut res = Vec::new();
+        let mut cursor = self.start;
+        for other in others {
+            if other.start > cursor {
+                res.push(Interval::new(cursor, other.start));
+            }
+            cursor = cursor.max(other.end);
+        }
+        if cursor < self.end {
+            res.push(Interval::new(cursor, self.end));
+        }
+
+        res
+    }
+
+    // occurrences of `span` starting at `time` on each day in `tz` that overlap the interval
+    pub fn daily(&self, time: Time, span: Span, tz: &TimeZone) -> Vec<Interval> {
+        let mut occurrences = Vec::new();
+        let mut day = self.start.to_zoned(tz.clone()).date();
+        loop {
+            let start = day
+                .to_datetime(time)
+                .to_zoned(tz.clone())
+                .expect("Failed to convert daily start to timestamp")
+                .timestamp();
+            if start >= self.end {
+                break;
+            }
+            if start + span > self.start {
+                occurrences.push(Interval::from_span(start, span));
+            }
+            day = day.tomorrow().expect("Failed to get next day");
+        }
+
+        occurrences
+    }
+
+    pub fn as_local_pair(&self, tz: &TimeZone) -> (Zoned, Zoned) {
+        (
+            self.start.to_zoned(tz.clone()),
+            self.end.to_zoned(tz.clone()),
+        )
+    }
+
+    // rounds the start in `tz`, e.g. `Unit::Day` with `RoundMode::Trunc` gives local midnight
+    pub fn round_start(&self, unit: Unit, mode: RoundMode, tz: &TimeZone) -> Timestamp {
+        self.start
+            .to_zoned(tz.clone())
+            .round(ZonedRound::new().smallest(unit).mode(mode))
+            .expect("Failed to round timestamp")
+            .timestamp()
+    }
+}
+
+// a daily time-of-day window, e.g. 09:00-12:00 every day. `end` is exclusive. a window ending
+// before it starts crosses midnight, e.g. 22:00-02:00 runs into the next day
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeWindow {
+    pub start: Time,
+    pub end: Time,
+}
+
+impl TimeWindow {
+    pub fn new(start: Time, end: Time) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains(&self, timestamp: Timestamp, tz: &TimeZone) -> bool {
+        let time = timestamp.to_zoned(tz.clone()).time();
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+
+    // the window on each day in `tz` that overlaps `interval`, including the one opened the day
+    // before when the window crosses midnight
+    pub fn occurrences(&self, interval: &Interval, tz: &TimeZone) -> Vec<Interval> {
+        let at = |day: Date, time: Time| {
+            day.to_datetime(time)
+                .to_zoned(tz.clone())
+                .expect("Failed to convert window bound to timestamp")
+                .timestamp()
+        };
+        let mut occurrences = Vec::new();
+        let mut day = interval
+            .start
+            .to_zoned(tz.clone())
+            .date()
+            .yesterday()
+            .expect("Failed to get previous day");
+        loop {
+            let next_day = day.tomorrow().expect("Failed to get next day");
+            let start = at(day, self.start);
+            if start >= interval.end {
+                break;
+            }
+            let end = at(if self.start < self.end { day } else { next_day }, self.end);
+            if end > interval.start && end > start {
+                occurrences.push(Interval::new(start, end));
+            }
+            day = next_day;
+        }
+
+        occurrences
+    }
+
+    // intervals of `interval` that fall outside of the window on each day in `tz`
+    pub fn complement(&self, interval: &Interval, tz: &TimeZone) -> Vec<Interval> {
+        interval.difference(&self.occurrences(interval, tz))
+    }
+
+    // `timestamp` if the window is open then, otherwise the next time it opens
+    pub fn next_opening(&self, timestamp: Timestamp, tz: &TimeZone) -> Timestamp {
+        if self.contains(timestamp, tz) {
+            return timestamp;
+        }
+        let day = timestamp.to_zoned(tz.clone()).date();
+        [day, day.tomorrow().expect("Failed to get next day")]
+            .into_iter()
+            .map(|day| {
+                day.to_datetime(self.start)
+                    .to_zoned(tz.clone())
+                    .expect("Failed to convert window start to timestamp")
+                    .timestamp()
+            })
+            .find(|start| *start > timestamp)
+            .expect("Failed to find the next window opening")
+    }
+}
+
+// `%R-%R`, the form `parse::parse_time_window` reads
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.strftime("%R"),
+            self.end.strftime("%R")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+    use jiff::{tz::offset, ToSpan};
+
+    #[test]
+    fn test_round_start() {
+        let interval = Interval::from_span("2025-03-05T13:45Z".parse().unwrap(), 1.hour());
+        let tz = TimeZone::fixed(offset(2));
+
+        assert_eq!(
+            interval.round_start(Unit::Day, RoundMode::Trunc, &tz),
+            "2025-03-04T22:00Z".parse::<Timestamp>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_as_local_pair() {
+        let interval = Interval::from_span("2025-03-05T13:45Z".parse().unwrap(), 1.hour());
+        let (start, end) = interval.as_local_pair(&TimeZone::fixed(offset(-5)));
+
+        assert_eq!(start.offset(), offset(-5));
+        assert_eq!(start.strftime("%F %R").to_string(), "2025-03-05 08:45");
+        assert_eq!(end.strftime("%F %R").to_string(), "2025-03-05 09:45");
+        assert_eq!(end.timestamp(), interval.end);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let interval = Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 90.minutes());
+
+        let json = json::to_string(&interval).unwrap();
+        assert_eq!(json::from_str::<Interval>(&json).unwrap(), interval);
+
+        let json = r#"{"start":"2025-03-05T10:00:00.25+01:00","end":"2025-03-05T10:30:00.999Z"}"#;
+        assert_eq!(json::from_str::<Interval>(json).unwrap(), interval);
+
+        let json = r#"{"start":"2025-03-05T10:30Z","end":"2025-03-05T09:00Z"}"#;
+        assert!(json::from_str::<Interval>(json).is_err());
+    }
+
+    #[test]
+    fn test_try_new() {
+        let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+        let interval = Interval::try_new(start, start + 1.hour()).unwrap();
+        assert_eq!(interval, Interval::from_span(start, 1.hour()));
+        assert_eq!(
+            Interval::try_new(start, start).unwrap(),
+            Interval::from_span(start, 0.hours())
+        );
+
+        let err = Interval::try_new(start + 1.hour(), start).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected the interval to end after it starts, got: \
+             2025-03-05T01:00:00Z - 2025-03-05T00:00:00Z"
+        );
+        assert!(Interval::try_new(start, start - 1.nanosecond()).is_err());
+    }
+
+    #[test]
+    fn test_clip_to() {
+        let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+        let bounds = Interval::from_span(start, 10.hours());
+
+        assert_eq!(
+            Interval::from_span(start + 8.hours(), 4.hours()).clip_to(&bounds),
+            Some(Interval::from_span(start + 8.hours(), 2.hours()))
+        );
+        assert_eq!(
+            Interval::from_span(start + 2.hours(), 1.hour()).clip_to(&bounds),
+            Some(Interval::from_span(start + 2.hours(), 1.hour()))
+        );
+        assert_eq!(
+            Interval::from_span(start + 10.hours(), 1.hour()).clip_to(&bounds),
+            None
+        );
+    }
+
+    #[test]
+    fn test_overlap() {
+        let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+        let interval = Interval::from_span(start, 4.hours());
+        let overlap = |offset: i64, hours: i64| {
+            let other = Interval::from_span(start + offset.hours(), hours.hours());
+            let overlap = interval.overlap(&other);
+            assert_eq!(overlap, other.overlap(&interval));
+            overlap
+        };
+
+        // contained
+        assert_eq!(
+            overlap(1, 2),
+            Some(Interval::from_span(start + 1.hour(), 2.hours()))
+        );
+        // partially overlapping
+        assert_eq!(
+            overlap(3, 2),
+            Some(Interval::from_span(start + 3.hours(), 1.hour()))
+        );
+        // touching
+        assert_eq!(overlap(4, 2), None);
+        // disjoint
+        assert_eq!(overlap(6, 1), None);
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let interval = Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 90.minutes());
+        let drifted = Interval::new(interval.start, interval.end - 1.second());
+
+        assert!(interval.approx_eq(&drifted, 1.second()));
+        assert!(drifted.approx_eq(&interval, 1.second()));
+        assert!(!interval.approx_eq(&drifted, Span::new()));
+        assert!(interval.approx_eq(&interval, Span::new()));
+    }
+
+    #[test]
+    fn test_relations() {
+        let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+        let interval = Interval::from_span(start + 2.hours(), 4.hours());
+        // other interval as start and end hours, then whether it intercepts, partially intercepts,
+        // is contained in and contains `interval`
+        for (other_start, other_end, intercepts, partially, contained, contains) in [
+            (2i64, 6i64, true, false, true, true),
+            (3, 5, true, false, true, false),
+            (2, 4, true, false, true, false),
+            (4, 6, true, false, true, false),
+            (1, 7, true, false, false, true),
+            (2, 7, true, false, false, true),
+            (1, 6, true, false, false, true),
+            (1, 3, true, true, false, false),
+            (5, 7, true, true, false, false),
+            (0, 2, false, false, false, false),
+            (6, 8, false, false, false, false),
+            (7, 8, false, false, false, false),
+        ] {
+            let other = Interval::new(start + other_start.hours(), start + other_end.hours());
+            let case = format!("{}h - {}h", other_start, other_end);
+            assert_eq!(interval.intercepts(&other), intercepts, "{}", case);
+            assert_eq!(other.intercepts(&interval), intercepts, "{}", case);
+            assert_eq!(interval.partially_intercepts(&other), partially, "{}", case);
+            assert_eq!(other.partially_intercepts(&interval), partially, "{}", case);
+            assert_eq!(interval.contains(&other), contained, "{}", case);
+            assert_eq!(other.contains(&interval), contains, "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_contains_timestamp() {
+        let interval = Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 2.hours());
+
+        assert!(interval.contains_timestamp(interval.start));
+        assert!(interval.contains_timestamp(interval.start + 1.hour()));
+        assert!(!interval.contains_timestamp(interval.end));
+        assert!(!interval.contains_timestamp(interval.start - 1.second()));
+    }
+
+    #[test]
+    fn test_difference() {
+        let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+        let interval = Interval::from_span(start, 10.hours());
+
+        assert_eq!(
+            interval.difference(&[
+                Interval::from_span(start + 6.hours(), 2.hours()),
+                Interval::from_span(start - 1.hour(), 2.hours()),
+                Interval::from_span(start + 3.hours(), 2.hours()),
+                Interval::from_span(start + 4.hours(), 1.hour()),
+                Interval::from_span(start + 11.hours(), 1.hour()),
+            ]),
+            vec![
+                Interval::from_span(start + 1.hour(), 2.hours()),
+                Interval::from_span(start + 5.hours(), 1.hour()),
+                Interval::from_span(start + 8.hours(), 2.hours()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+        let window = TimeWindow::new(Time::constant(22, 0, 0, 0), Time::constant(2, 0, 0, 0));
+        let tz = TimeZone::UTC;
+
+        assert!(window.contains(start + 23.hours(), &tz));
+        assert!(window.contains(start + 1.hour(), &tz));
+        assert!(!window.contains(start + 2.hours(), &tz));
+        assert_eq!(
+            window.complement(&Interval::from_span(start, 48.hours()), &tz),
+            vec![
+                Interval::from_span(start + 2.hours(), 20.hours()),
+                Interval::from_span(start + 26.hours(), 20.hours()),
+            ]
+        );
+        assert_eq!(
+            window.next_opening(start + 12.hours(), &tz),
+            start + 22.hours()
+        );
+        assert_eq!(window.next_opening(start + 1.hour(), &tz), start + 1.hour());
+    }
 }
@@ -0,0 +1,288 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yaml::{Mapping, Number, Value};
+use std::{error::Error, fmt::Write, iter::Peekable, str::CharIndices};
+
+// serializes `value` to json by way of `serde_yaml::Value`, so anything that serializes to yaml
+// serializes to json too. enum variants with data become `{"Variant": data}`, non-finite numbers
+// become `null`. see `from_str` for reading it back
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Box<dyn Error>> {
+    let mut res = String::new();
+    write_value(&mut res, &serde_yaml::to_value(value)?)?;
+    Ok(res)
+}
+
+fn write_value(res: &mut String, value: &Value) -> Result<(), Box<dyn Error>> {
+    match value {
+        Value::Null => res.push_str("null"),
+        Value::Bool(value) => write!(res, "{}", value)?,
+        Value::Number(number) if !number.is_finite() => res.push_str("null"),
+        Value::Number(number) => write!(res, "{}", number)?,
+        Value::String(value) => write_string(res, value)?,
+        Value::Sequence(values) => {
+            res.push('[');
+            for (idx, value) in values.iter().enumerate() {
+                if idx != 0 {
+                    res.push(',');
+                }
+                write_value(res, value)?;
+            }
+            res.push(']');
+        }
+        Value::Mapping(mapping) => {
+            res.push('{');
+            for (idx, (key, value)) in mapping.iter().enumerate() {
+                if idx != 0 {
+                    res.push(',');
+                }
+                match key {
+                    Value::String(key) => write_string(res, key)?,
+                    Value::Bool(_) | Value::Number(_) => {
+                        write_string(res, serde_yaml::to_string(key)?.trim_end())?
+                    }
+                    _ => return Err(format!("Unsupported json key: {:?}", key).into()),
+                }
+                res.push(':');
+                write_value(res, value)?;
+            }
+            res.push('}');
+        }
+        Value::Tagged(tagged) => {
+            res.push('{');
+            write_string(res, tagged.tag.to_string().trim_start_matches('!'))?;
+            res.push(':');
+            write_value(res, &tagged.value)?;
+            res.push('}');
+        }
+    }
+
+    Ok(())
+}
+
+fn write_string(res: &mut String, value: &str) -> Result<(), Box<dyn Error>> {
+    res.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_control() => write!(res, "\\u{:04x}", c as u32)?,
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+
+    Ok(())
+}
+
+// deserializes strict json by way of `serde_yaml::Value`, the counterpart of `to_string`. enum
+// variants with data are not read back from `{"Variant": data}`
+pub fn from_str<T: DeserializeOwned>(json: &str) -> Result<T, Box<dyn Error>> {
+    let mut chars = json.char_indices().peekable();
+    let value = read_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    if let Some((idx, c)) = chars.next() {
+        return Err(format!("Expected the end of the json at {}, got '{}'", idx, c).into());
+    }
+    Ok(serde_yaml::from_value(value)?)
+}
+
+fn skip_whitespace(chars: &mut Peekable<CharIndices>) {
+    while chars.next_if(|(_, c)| c.is_ascii_whitespace()).is_some() {}
+}
+
+fn expect(chars: &mut Peekable<CharIndices>, expected: char) -> Result<(), Box<dyn Error>> {
+    match chars.next() {
+        Some((_, c)) if c == expected => Ok(()),
+        Some((idx, c)) => Err(format!("Expected '{}' at {}, got '{}'", expected, idx, c).into()),
+        None => Err(format!("Expected '{}', got the end of the json", expected).into()),
+    }
+}
+
+fn read_value(chars: &mut Peekable<CharIndices>) -> Result<Value, Box<dyn Error>> {
+    skip_whitespace(chars);
+    let Some(&(idx, c)) = chars.peek() else {
+        return Err("Expected a value, got the end of the json".into());
+    };
+    match c {
+        'n' | 't' | 'f' => {
+            let (literal, value) = match c {
+                'n' => ("null", Value::Null),
+                't' => ("true", Value::Bool(true)),
+                _ => ("false", Value::Bool(false)),
+            };
+            for expected in literal.chars() {
+                expect(chars, expected)?;
+            }
+            Ok(value)
+        }
+        '"' => Ok(Value::String(read_string(chars)?)),
+        '-' | '0'..='9' => {
+            let mut number = String::new();
+            while let Some((_, c)) =
+                chars.next_if(|(_, c)| c.is_ascii_digit() || "+-.eE".contains(*c))
+            {
+                number.push(c);
+            }
+            let number = if let Ok(number) = number.parse::<u64>() {
+                Number::from(number)
+            } else if let Ok(number) = number.parse::<i64>() {
+                Number::from(number)
+            } else {
+                Number::from(
+                    number
+                        .parse::<f64>()
+                        .map_err(|_| format!("Expected a number at {}, got {}", idx, number))?,
+                )
+            };
+            Ok(Value::Number(number))
+        }
+        '[' => {
+            chars.next();
+            let mut values = Vec::new();
+            skip_whitespace(chars);
+            if chars.next_if(|(_, c)| *c == ']').is_some() {
+                return Ok(Value::Sequence(values));
+            }
+            loop {
+                values.push(read_value(chars)?);
+                skip_whitespace(chars);
+                if chars.next_if(|(_, c)| *c == ',').is_none() {
+                    expect(chars, ']')?;
+                    return Ok(Value::Sequence(values));
+                }
+            }
+        }
+        '{' => {
+            chars.next();
+            let mut mapping = Mapping::new();
+            skip_whitespace(chars);
+            if chars.next_if(|(_, c)| *c == '}').is_some() {
+                return Ok(Value::Mapping(mapping));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = read_string(chars)?;
+                skip_whitespace(chars);
+                expect(chars, ':')?;
+                mapping.insert(Value::String(key), read_value(chars)?);
+                skip_whitespace(chars);
+                if chars.next_if(|(_, c)| *c == ',').is_none() {
+                    expect(chars, '}')?;
+                    return Ok(Value::Mapping(mapping));
+                }
+            }
+        }
+        c => Err(format!("Expected a value at {}, got '{}'", idx, c).into()),
+    }
+}
+
+fn read_string(chars: &mut Peekable<CharIndices>) -> Result<String, Box<dyn Error>> {
+    expect(chars, '"')?;
+    let mut res = String::new();
+    loop {
+        match chars.next() {
+            Some((_, '"')) => return Ok(res),
+            Some((idx, '\\')) => match chars.next().map(|(_, c)| c) {
+                Some('"') => res.push('"'),
+                Some('\\') => res.push('\\'),
+                Some('/') => res.push('/'),
+                Some('b') => res.push('\u{8}'),
+                Some('f') => res.push('\u{c}'),
+                Some('n') => res.push('\n'),
+                Some('r') => res.push('\r'),
+                Some('t') => res.push('\t'),
+                Some('u') => {
+                    let mut code = read_code_unit(chars)?;
+                    // characters outside of the basic plane are escaped as a surrogate pair
+                    if (0xd800..0xdc00).contains(&code) {
+                        expect(chars, '\\')?;
+                        expect(chars, 'u')?;
+                        let low = read_code_unit(chars)?;
+                        code = 0x10000 + ((code - 0xd800) << 10) + low.wrapping_sub(0xdc00);
+                    }
+                    res.push(
+                        char::from_u32(code)
+                            .ok_or_else(|| format!("Expected a valid escape at {}", idx))?,
+                    );
+                }
+                _ => return Err(format!("Expected a valid escape at {}", idx).into()),
+            },
+            Some((idx, c)) if c.is_control() => {
+                return Err(format!("Expected an escaped control character at {}", idx).into())
+            }
+            Some((_, c)) => res.push(c),
+            None => return Err("Expected '\"', got the end of the json".into()),
+        }
+    }
+}
+
+fn read_code_unit(chars: &mut Peekable<CharIndices>) -> Result<u32, Box<dyn Error>> {
+    let mut code = 0;
+    for _ in 0..4 {
+        let digit = chars
+            .next()
+            .and_then(|(_, c)| c.to_digit(16))
+            .ok_or("Expected 4 hex digits after \\u")?;
+        code = code * 16 + digit;
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Entry {
+        Unit,
+        Data(f32),
+    }
+
+    #[test]
+    fn test_to_string() {
+        let value = BTreeMap::from([
+            ("a \"quoted\"\nkey", vec![Entry::Unit, Entry::Data(1.5)]),
+            ("b", vec![Entry::Data(f32::NAN)]),
+        ]);
+
+        assert_eq!(
+            to_string(&value).unwrap(),
+            r#"{"a \"quoted\"\nkey":["Unit",{"Data":1.5}],"b":[{"Data":null}]}"#
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        let value = BTreeMap::from([
+            ("a \"quoted\"\nkey\t\u{1}", vec![Entry::Unit]),
+            ("b", vec![]),
+        ]);
+        assert_eq!(
+            from_str::<BTreeMap<String, Vec<Entry>>>(&to_string(&value).unwrap()).unwrap(),
+            value
+                .into_iter()
+                .map(|(key, entries)| (key.to_string(), entries))
+                .collect()
+        );
+
+        let json =
+            r#" { "n" : [ -1 , 2.5e1 , 18446744073709551615 ] , "s" : "\u00e9\ud83d\ude00\/" } "#;
+        let value: BTreeMap<String, Value> = from_str(json).unwrap();
+        assert_eq!(
+            value["n"],
+            serde_yaml::from_str::<Value>("[-1, 25.0, 18446744073709551615]").unwrap()
+        );
+        assert_eq!(value["s"], Value::String("é😀/".to_string()));
+        assert_eq!(from_str::<Option<bool>>("null").unwrap(), None);
+        assert!(from_str::<bool>(" true ").unwrap());
+
+        // yaml that is not json is rejected
+        for json in ["{a: 1}", "[1,]", "'a'", "[1] 2", "\"a", "\"\\x\"", "tru"] {
+            assert!(from_str::<Value>(json).is_err(), "{}", json);
+        }
+    }
+}
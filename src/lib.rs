@@ -0,0 +1,15 @@
+pub mod allocators;
+pub mod chrono;
+pub mod clock;
+pub mod group_by;
+pub mod heuristics;
+pub mod interval;
+pub mod json;
+pub mod parse;
+pub mod runner;
+pub mod scheduler;
+pub mod solar;
+pub mod tasks;
+mod tests;
+
+use crate::scheduler::Scheduler;
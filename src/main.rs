@@ -1,34 +1,226 @@
-mod allocators;
-mod chrono;
-mod group_by;
-mod heuristics;
-mod interval;
-mod scheduler;
-mod tasks;
-mod tests;
-
-use crate::scheduler::{Schedule, Scheduler, SchedulerConfig};
-use std::{error::Error, fs};
+use jiff::Timestamp;
+use panini::{
+    clock::SystemClock,
+    json, runner,
+    scheduler::{MidnightStyle, Scheduler, SchedulerConfig, StartRounding},
+    tasks,
+};
+use std::{
+    env,
+    error::Error,
+    fs::{self, File},
+    io::{self, Read},
+    process,
+};
 
 const CONFIG_FILE: &str = "data/config.yaml";
 const SCHEDULE_FILE: &str = "data/schedule.yaml";
+const DEFAULT_TOP: usize = 5;
+const DEFAULT_BENCHMARK_TASKS: usize = 1000;
 
+// `-` reads the config from stdin, in which case the schedule goes to stdout unless `--out` is set.
+// `--out` can be repeated, the format of each output follows its extension. `--top [N]` prints
+// only the next N scheduled tasks instead. `--midnight-end keep|24:00` picks how intervals ending
+// at midnight are shown in every output, by default `24:00` in yaml, markdown and `--top` only.
+// `--since [TIMESTAMP]` leaves out intervals ending before it, `now` by default. configs with
+// `resources` get one yaml schedule per resource and accept only `--out` and `--since`.
+// `--check-calendar PATH` lists the events of a previously exported ics file that the new
+// schedule moved or removed. `--explain` adds why each task block was placed where it is to
+// the yaml, json, markdown and `--top` output. `--lunch '%R / span'` and `--round-start
+// none|hour|day` replace the `lunch` and `round_start` of the config
 fn run() -> Result<(), Box<dyn Error>> {
-    let config = serde_yaml::from_str::<SchedulerConfig>(&fs::read_to_string(CONFIG_FILE)?)?;
+    let mut config_path = CONFIG_FILE.to_string();
+    let mut schedule_paths = Vec::new();
+    let mut trace_path = None;
+    let mut since = None;
+    let mut worked_path = None;
+    let mut diagnostics_path = None;
+    let mut resolved_tasks_path = None;
+    let mut top = None;
+    let mut show_plans = false;
+    let mut explain = false;
+    let mut benchmark = None;
+    let mut midnight_end = None;
+    let mut calendar_path = None;
+    let mut lunch = None;
+    let mut round_start = None;
 
-    let mut scheduler = Scheduler::try_from(config)?
-        .add_heuristic(heuristics::dependency)
-        .add_heuristic(heuristics::volume)
-        .add_heuristic(heuristics::deadline)
-        .add_heuristic(heuristics::priority)
-        .add_heuristic(heuristics::locality);
+    let mut args = env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config_path = args.next().ok_or("Expected a path after --config")?,
+            "--out" => schedule_paths.push(args.next().ok_or("Expected a path after --out")?),
+            "--trace" => trace_path = Some(args.next().ok_or("Expected a path after --trace")?),
+            "--diagnostics" => {
+                diagnostics_path = Some(args.next().ok_or("Expected a path after --diagnostics")?)
+            }
+            "--resolved-tasks" => {
+                resolved_tasks_path = Some(
+                    args.next()
+                        .ok_or("Expected a path after --resolved-tasks")?,
+                )
+            }
+            "--top" => {
+                let count = args.next_if(|count| !count.starts_with("--"));
+                top = Some(
+                    count
+                        .map_or(Ok(DEFAULT_TOP), |count| count.parse())
+                        .map_err(|_| "Expected a number after --top")?,
+                );
+            }
+            "--show-plans" => show_plans = true,
+            "--explain" => explain = true,
+            "--check-calendar" => {
+                calendar_path = Some(
+                    args.next()
+                        .ok_or("Expected a path after --check-calendar")?,
+                )
+            }
+            "--midnight-end" => {
+                midnight_end = Some(match args.next().as_deref() {
+                    Some("keep") => MidnightStyle::Keep,
+                    Some("24:00") => MidnightStyle::As24,
+                    _ => return Err("Expected keep or 24:00 after --midnight-end".into()),
+                })
+            }
+            "--lunch" => lunch = Some(args.next().ok_or("Expected '%R / span' after --lunch")?),
+            "--round-start" => {
+                round_start = Some(match args.next().as_deref() {
+                    Some("none") => StartRounding::None,
+                    Some("hour") => StartRounding::Hour,
+                    Some("day") => StartRounding::Day,
+                    _ => return Err("Expected none, hour or day after --round-start".into()),
+                })
+            }
+            // undocumented, schedules a generated problem of N tasks and reports the timing
+            "--benchmark" => {
+                let count = args.next_if(|count| !count.starts_with("--"));
+                benchmark = Some(
+                    count
+                        .map_or(Ok(DEFAULT_BENCHMARK_TASKS), |count| count.parse())
+                        .map_err(|_| "Expected a number after --benchmark")?,
+                );
+            }
+            "--worked" => worked_path = Some(args.next().ok_or("Expected a path after --worked")?),
+            "--since" => {
+                let timestamp = args.next_if(|timestamp| !timestamp.starts_with("--"));
+                since = Some(runner::parse_timestamp(
+                    timestamp.as_deref().unwrap_or("now"),
+                )?);
+            }
+            _ => return Err(format!("Unknown argument: {}", arg).into()),
+        }
+    }
+
+    if let Some(task_count) = benchmark {
+        println!("{}", runner::benchmark(task_count)?);
+        return Ok(());
+    }
+
+    let mut config_yaml = String::new();
+    if config_path == "-" {
+        io::stdin().read_to_string(&mut config_yaml)?;
+    } else {
+        File::open(config_path.as_str())?.read_to_string(&mut config_yaml)?;
+    }
+    let mut config = SchedulerConfig::from_yaml(&config_yaml)?;
+    if let Some(lunch) = lunch {
+        config = config.with_lunch(lunch);
+    }
+    if let Some(round_start) = round_start {
+        config = config.with_round_start(round_start);
+    }
+    if schedule_paths.is_empty() {
+        schedule_paths.push(
+            if config_path == "-" {
+                "-"
+            } else {
+                SCHEDULE_FILE
+            }
+            .to_string(),
+        );
+    }
+    for path in &schedule_paths {
+        runner::OutputFormat::from_path(path)?;
+    }
 
+    let mut team = runner::team_from_config(config, &SystemClock)?;
+    if team.len() > 1 || !team[0].0.is_empty() {
+        let unsupported = [
+            ("--worked", worked_path.is_some()),
+            ("--trace", trace_path.is_some()),
+            ("--top", top.is_some()),
+            ("--diagnostics", diagnostics_path.is_some()),
+            ("--resolved-tasks", resolved_tasks_path.is_some()),
+            ("--midnight-end", midnight_end.is_some()),
+            ("--check-calendar", calendar_path.is_some()),
+            ("--explain", explain),
+        ];
+        if let Some((flag, _)) = unsupported.iter().find(|(_, set)| *set) {
+            return Err(format!("{} is not supported for configs with resources", flag).into());
+        }
+        return run_team(team, since, &schedule_paths);
+    }
+    let (_, mut scheduler) = team.remove(0);
+    if let Some(worked_path) = worked_path {
+        let worked = fs::read_to_string(worked_path)?;
+        for warning in tasks::reconcile_worked(&mut scheduler.tasks, &worked, &SystemClock)? {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+    for problem in scheduler.validate() {
+        eprintln!("Warning: {}", problem);
+    }
+    if let Some(resolved_tasks_path) = resolved_tasks_path {
+        let resolved_tasks = scheduler.export_tasks_with_computed_fields();
+        fs::write(resolved_tasks_path, serde_yaml::to_string(&resolved_tasks)?)?;
+    }
+    if let Some(diagnostics_path) = diagnostics_path {
+        fs::write(diagnostics_path, json::to_string(&scheduler.diagnostics())?)?;
+    }
+    if trace_path.is_some() {
+        scheduler = scheduler.with_trace();
+    }
+    if explain {
+        scheduler = scheduler.with_reasons();
+    }
     scheduler.schedule();
-    let schedule = Schedule::from(&scheduler);
+    match top {
+        Some(count) => runner::write_top(
+            &scheduler,
+            since,
+            count,
+            show_plans,
+            midnight_end.unwrap_or(MidnightStyle::As24),
+            io::stdout(),
+        )?,
+        None => runner::write_outputs(&scheduler, since, &schedule_paths, midnight_end)?,
+    }
 
-    fs::write(SCHEDULE_FILE, serde_yaml::to_string(&schedule)?)?;
+    if let Some(calendar_path) = calendar_path {
+        let calendar = runner::read_ics(&fs::read_to_string(calendar_path)?)?;
+        for event in runner::stale_events(&calendar, &scheduler, since) {
+            eprintln!("Stale: {}", event);
+        }
+    }
+    if let Some(trace_path) = trace_path {
+        fs::write(trace_path, json::to_string(&scheduler.take_trace())?)?;
+    }
 
-    for idx in scheduler.get_missed_deadlines_tasks() {
+    for (idx, reason) in &scheduler.dropped {
+        eprintln!("Dropped: {}, {}", scheduler.tasks[*idx].description, reason);
+    }
+    for (idx, interval) in &scheduler.clipped {
+        let (start, end) = interval.as_local_pair(&scheduler.tz);
+        eprintln!(
+            "Clipped: {}, {} - {} only partly fits",
+            scheduler.tasks[*idx].description,
+            start.strftime("%F %R"),
+            end.strftime("%R")
+        );
+    }
+    let missed = scheduler.get_missed_deadlines_tasks();
+    for &idx in &missed {
         let task = &scheduler.tasks[idx];
         eprintln!(
             "Missed deadline: {}, needs {} more hour(s)",
@@ -36,10 +228,78 @@ fn run() -> Result<(), Box<dyn Error>> {
             task.volume - scheduler.get_total_task_hours(idx)
         );
     }
+    if !missed.is_empty() || !scheduler.dropped.is_empty() {
+        for suggestion in scheduler.rebalance_priorities() {
+            eprintln!("Suggestion: {}", suggestion);
+        }
+    }
+
+    Ok(())
+}
+
+// schedules each resource of the config on its own. only the yaml schedule is written
+fn run_team(
+    mut team: Vec<(String, Scheduler)>,
+    since: Option<Timestamp>,
+    schedule_paths: &[String],
+) -> Result<(), Box<dyn Error>> {
+    for path in schedule_paths {
+        if runner::OutputFormat::from_path(path)? != runner::OutputFormat::Yaml {
+            return Err(format!(
+                "Schedules with resources can only be written as yaml: {}",
+                path
+            )
+            .into());
+        }
+    }
+    for (name, scheduler) in &mut team {
+        for problem in scheduler.validate() {
+            eprintln!("Warning: {} ({})", problem, name);
+        }
+        scheduler.schedule();
+    }
+    for path in schedule_paths {
+        if path == "-" {
+            runner::write_team_schedule(&team, since, io::stdout())?;
+        } else {
+            runner::write_team_schedule(&team, since, File::create(path)?)?;
+        }
+    }
+
+    for (name, scheduler) in &team {
+        for (idx, reason) in &scheduler.dropped {
+            eprintln!(
+                "Dropped: {} ({}), {}",
+                scheduler.tasks[*idx].description, name, reason
+            );
+        }
+        for (idx, interval) in &scheduler.clipped {
+            let (start, end) = interval.as_local_pair(&scheduler.tz);
+            eprintln!(
+                "Clipped: {} ({}), {} - {} only partly fits",
+                scheduler.tasks[*idx].description,
+                name,
+                start.strftime("%F %R"),
+                end.strftime("%R")
+            );
+        }
+        for idx in scheduler.get_missed_deadlines_tasks() {
+            let task = &scheduler.tasks[idx];
+            eprintln!(
+                "Missed deadline: {} ({}), needs {} more hour(s)",
+                task.description,
+                name,
+                task.volume - scheduler.get_total_task_hours(idx)
+            );
+        }
+    }
 
     Ok(())
 }
 
 fn main() {
-    run().expect("Failed to run the scheduler");
+    if let Err(err) = run() {
+        eprintln!("Failed to run the scheduler: {}", err);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,99 @@
+use crate::{clock::Clock, interval::TimeWindow};
+use jiff::{
+    civil::{DateTime, Time},
+    tz::TimeZone,
+    Span, SpanRound, Timestamp, Unit,
+};
+use std::error::Error;
+
+// parses `now`, a local `%F %R` datetime or an rfc 3339 timestamp
+pub fn parse_timestamp_with_clock(
+    value: &str,
+    clock: &dyn Clock,
+) -> Result<Timestamp, Box<dyn Error>> {
+    if value == "now" {
+        return Ok(clock.now());
+    }
+    match DateTime::strptime("%F %R", value) {
+        Ok(datetime) => Ok(datetime.to_zoned(TimeZone::system())?.timestamp()),
+        Err(_) => Ok(value.parse::<Timestamp>()?),
+    }
+}
+
+// parses both iso 8601 durations (`PT2H30M`, `P1D`) and jiff's friendly format (`2h 30m`). days
+// are balanced into 24-hour units, so the span can be added to timestamps
+pub fn parse_span(value: &str) -> Result<Span, Box<dyn Error>> {
+    let span = value.trim().parse::<Span>()?;
+    Ok(span.round(SpanRound::new().largest(Unit::Hour).days_are_24_hours())?)
+}
+
+// `%R-%R`, where an end before the start crosses midnight, e.g. `22:00-02:00`
+pub fn parse_time_window(value: &str) -> Result<TimeWindow, Box<dyn Error>> {
+    let (start, end) = value
+        .split_once('-')
+        .ok_or_else(|| format!("Expected the form '%R-%R', got: {}", value))?;
+    let (start, end) = (
+        Time::strptime("%R", start.trim())?,
+        Time::strptime("%R", end.trim())?,
+    );
+    if end == start {
+        return Err(format!("Expected the window to end after it starts, got: {}", value).into());
+    }
+    Ok(TimeWindow::new(start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use jiff::{civil::time, ToSpan};
+
+    #[test]
+    fn test_parse_timestamp_with_clock() {
+        let now = "2025-03-05T09:30Z".parse().unwrap();
+        let clock = FixedClock(now);
+        assert_eq!(parse_timestamp_with_clock("now", &clock).unwrap(), now);
+        assert_eq!(
+            parse_timestamp_with_clock("2025-03-05T12:00Z", &clock).unwrap(),
+            "2025-03-05T12:00Z".parse::<Timestamp>().unwrap()
+        );
+        assert_eq!(
+            parse_timestamp_with_clock("2025-03-05 12:00", &clock).unwrap(),
+            DateTime::constant(2025, 3, 5, 12, 0, 0, 0)
+                .to_zoned(TimeZone::system())
+                .unwrap()
+                .timestamp()
+        );
+        assert!(parse_timestamp_with_clock("tomorrow", &clock).is_err());
+    }
+
+    #[test]
+    fn test_parse_span() {
+        assert_eq!(
+            parse_span("PT90M").unwrap().fieldwise(),
+            1.hour().minutes(30)
+        );
+        assert_eq!(parse_span("P1DT2H").unwrap().fieldwise(), 26.hours());
+        assert_eq!(
+            parse_span("1h 30m").unwrap().fieldwise(),
+            1.hour().minutes(30)
+        );
+        assert!(parse_span("P1M").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_window() {
+        let window = parse_time_window("09:00-17:30").unwrap();
+        assert_eq!(
+            window,
+            TimeWindow::new(time(9, 0, 0, 0), time(17, 30, 0, 0))
+        );
+        assert_eq!(window.to_string(), "09:00-17:30");
+        assert_eq!(
+            parse_time_window("22:00 - 02:00").unwrap(),
+            TimeWindow::new(time(22, 0, 0, 0), time(2, 0, 0, 0))
+        );
+        assert!(parse_time_window("09:00-09:00").is_err());
+        assert!(parse_time_window("09:00").is_err());
+    }
+}
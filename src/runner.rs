@@ -0,0 +1,1483 @@
+use crate::{
+    allocators::{Plans, TaskAllocatorWithPlans},
+    clock::{Clock, FixedClock, SystemClock},
+    heuristics::{self, Heuristic},
+    interval::Interval,
+    json,
+    parse::parse_timestamp_with_clock,
+    scheduler::{MidnightStyle, Schedule, Scheduler, SchedulerConfig},
+    tasks::Task,
+};
+use jiff::{civil::DateTime, tz::TimeZone, Span, Timestamp};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Write as _},
+    fs::File,
+    io::{self, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+// reads the config yaml from `config`, schedules it with the default heuristics and writes the
+// schedule yaml to `schedule`
+pub fn run(config: impl Read, schedule: impl Write) -> Result<Scheduler, Box<dyn Error>> {
+    let mut scheduler = load(config)?;
+    scheduler.schedule();
+    write_schedule(&scheduler, None, schedule)?;
+
+    Ok(scheduler)
+}
+
+// schedules `tasks` around `plans` on `interval` in blocks of `granularity`, for library users
+// that do not need the scheduler itself
+pub fn plan(
+    tasks: impl Into<Vec<Task>>,
+    plans: Plans,
+    interval: Interval,
+    heuristics: Vec<Heuristic>,
+    granularity: Span,
+) -> Result<Schedule, Box<dyn Error>> {
+    if !granularity.is_positive() {
+        return Err(format!("Expected a positive granularity, got {}", granularity).into());
+    }
+    if interval.end <= interval.start {
+        return Err("Expected the interval to end after it starts".into());
+    }
+
+    let allocator = TaskAllocatorWithPlans {
+        granularity,
+        plans,
+        ..Default::default()
+    };
+    let mut scheduler = heuristics.into_iter().fold(
+        Scheduler::new(allocator, tasks.into(), interval),
+        Scheduler::add_heuristic,
+    );
+    scheduler.schedule();
+
+    Ok(Schedule::from(&scheduler))
+}
+
+// reads the config yaml from `config` into a scheduler with the default heuristics
+pub fn load(config: impl Read) -> Result<Scheduler, Box<dyn Error>> {
+    load_with_clock(config, &SystemClock)
+}
+
+// like `load`, with `now` in the config taken from `clock`
+pub fn load_with_clock(
+    mut config: impl Read,
+    clock: &dyn Clock,
+) -> Result<Scheduler, Box<dyn Error>> {
+    let mut config_yaml = String::new();
+    config.read_to_string(&mut config_yaml)?;
+    let config = SchedulerConfig::from_yaml(&config_yaml)?;
+
+    Ok(with_default_heuristics(config.into_scheduler(clock)?))
+}
+
+// like `load_with_clock`, with the tasks split between the config's resources. see
+// `SchedulerConfig::into_team`
+pub fn load_team(
+    mut config: impl Read,
+    clock: &dyn Clock,
+) -> Result<Vec<(String, Scheduler)>, Box<dyn Error>> {
+    let mut config_yaml = String::new();
+    config.read_to_string(&mut config_yaml)?;
+    team_from_config(SchedulerConfig::from_yaml(&config_yaml)?, clock)
+}
+
+// like `load_team`, for a config already read, e.g. to change it from the command line first
+pub fn team_from_config(
+    config: SchedulerConfig,
+    clock: &dyn Clock,
+) -> Result<Vec<(String, Scheduler)>, Box<dyn Error>> {
+    Ok(config
+        .into_team(clock)?
+        .into_iter()
+        .map(|(name, scheduler)| (name, with_default_heuristics(scheduler)))
+        .collect())
+}
+
+// writes the schedule yaml of each resource under its name
+pub fn write_team_schedule(
+    team: &[(String, Scheduler)],
+    since: Option<Timestamp>,
+    mut schedule: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let schedules: BTreeMap<_, _> = team
+        .iter()
+        .map(|(name, scheduler)| {
+            (
+                name,
+                schedule_with_unscheduled(scheduler, since, MidnightStyle::As24),
+            )
+        })
+        .collect();
+    schedule.write_all(serde_yaml::to_string(&schedules)?.as_bytes())?;
+    Ok(())
+}
+
+fn with_default_heuristics(scheduler: Scheduler) -> Scheduler {
+    scheduler
+        .add_named_heuristic("dependency", heuristics::dependency)
+        .add_named_heuristic("milestone", heuristics::milestone)
+        .add_named_heuristic("soft_dependency", heuristics::soft_dependency)
+        .add_named_heuristic("volume", heuristics::volume)
+        .add_named_heuristic("lead_deadline", heuristics::lead_deadline)
+        .add_named_heuristic("priority", heuristics::priority)
+        .add_named_heuristic("time_window", heuristics::time_window)
+        .add_named_heuristic("weekly_budget", heuristics::weekly_budget)
+        .add_named_heuristic("daily_volume", heuristics::daily_volume)
+        .add_named_heuristic("max_tasks_per_day", heuristics::max_tasks_per_day)
+        .add_named_heuristic("exclusive_group", heuristics::exclusive_group)
+        .add_named_heuristic("context", heuristics::context)
+        .add_named_heuristic("locality", heuristics::locality)
+}
+
+// writes the schedule yaml, leaving out intervals that end before `since`. tasks that got no time
+// are listed with the reason under `Unscheduled`
+pub fn write_schedule(
+    scheduler: &Scheduler,
+    since: Option<Timestamp>,
+    schedule: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    write_schedule_with(
+        scheduler,
+        since,
+        OutputFormat::Yaml,
+        MidnightStyle::As24,
+        schedule,
+    )
+}
+
+fn schedule_with_unscheduled(
+    scheduler: &Scheduler,
+    since: Option<Timestamp>,
+    midnight_end: MidnightStyle,
+) -> Schedule {
+    let mut schedule_since =
+        scheduler.get_schedule_since_with(since.unwrap_or(Timestamp::MIN), midnight_end);
+    let unscheduled = scheduler.get_unscheduled_tasks();
+    if !unscheduled.is_empty() {
+        schedule_since.insert(
+            "Unscheduled".to_string(),
+            unscheduled
+                .into_iter()
+                .map(|(idx, reason)| (scheduler.tasks[idx].description.clone(), reason))
+                .collect(),
+        );
+    }
+    if scheduler.reasons.is_some() {
+        schedule_since.insert(
+            "Reasons".to_string(),
+            reasons_since(scheduler, since.unwrap_or(Timestamp::MIN), midnight_end),
+        );
+    }
+    schedule_since
+}
+
+// recorded reasons of the scheduled intervals ending after `since`, by `%F %R - %R` of the
+// interval as clipped to `since`
+fn reasons_since(
+    scheduler: &Scheduler,
+    since: Timestamp,
+    midnight_end: MidnightStyle,
+) -> BTreeMap<String, String> {
+    scheduler
+        .timeline()
+        .filter(|(_, interval)| interval.end > since)
+        .filter_map(|(task_idx, interval)| {
+            let reason = scheduler.reason(task_idx, interval.start)?;
+            let clipped = Interval::new(interval.start.max(since), interval.end);
+            let (start, end) = clipped.as_local_pair(&scheduler.tz);
+            let (_, end) = midnight_end.display_end(&end);
+            Some((
+                format!("{} - {}", start.strftime("%F %R"), end),
+                reason.to_string(),
+            ))
+        })
+        .collect()
+}
+
+// formats the schedule can be written in, picked by the file extension
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Yaml,
+    Json,
+    Csv,
+    Markdown,
+    Ics,
+}
+
+impl OutputFormat {
+    // `-` is stdout, which gets yaml
+    pub fn from_path(path: &str) -> Result<Self, Box<dyn Error>> {
+        if path == "-" {
+            return Ok(Self::Yaml);
+        }
+        match Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("yaml" | "yml") => Ok(Self::Yaml),
+            Some("json") => Ok(Self::Json),
+            Some("csv") => Ok(Self::Csv),
+            Some("md") => Ok(Self::Markdown),
+            Some("ics") => Ok(Self::Ics),
+            _ => Err(format!(
+                "Unknown output format of {}, expected .yaml, .json, .csv, .md or .ics",
+                path
+            )
+            .into()),
+        }
+    }
+
+    // machine readable formats keep `00:00`, as `24:00` trips up some parsers
+    pub fn default_midnight_end(self) -> MidnightStyle {
+        match self {
+            Self::Yaml | Self::Markdown => MidnightStyle::As24,
+            Self::Json | Self::Csv | Self::Ics => MidnightStyle::Keep,
+        }
+    }
+}
+
+// like `write_schedule` in any of the output formats. only yaml and json list the unscheduled
+// tasks, the others cover the scheduled time alone
+pub fn write_schedule_as(
+    scheduler: &Scheduler,
+    since: Option<Timestamp>,
+    format: OutputFormat,
+    output: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    write_schedule_with(
+        scheduler,
+        since,
+        format,
+        format.default_midnight_end(),
+        output,
+    )
+}
+
+// like `write_schedule_as`, with intervals ending at midnight shown as `midnight_end`. ics always
+// uses utc timestamps
+pub fn write_schedule_with(
+    scheduler: &Scheduler,
+    since: Option<Timestamp>,
+    format: OutputFormat,
+    midnight_end: MidnightStyle,
+    mut output: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let intervals = || {
+        let mut intervals = scheduler.get_intervals_since(since.unwrap_or(Timestamp::MIN));
+        intervals.sort_by(|(_, a), (_, b)| a.cmp(b));
+        intervals
+    };
+
+    let mut res = String::new();
+    match format {
+        OutputFormat::Yaml => {
+            res = serde_yaml::to_string(&schedule_with_unscheduled(scheduler, since, midnight_end))?
+        }
+        OutputFormat::Json => {
+            res = json::to_string(&schedule_with_unscheduled(scheduler, since, midnight_end))?
+        }
+        OutputFormat::Csv => {
+            res.push_str("start,end,description\n");
+            for (description, interval) in intervals() {
+                let (start, end) = interval.as_local_pair(&scheduler.tz);
+                let (end_date, end_time) = midnight_end.display_end(&end);
+                writeln!(
+                    res,
+                    "{},{} {},{}",
+                    start.strftime("%F %R"),
+                    end_date,
+                    end_time,
+                    csv_field(&description)
+                )?;
+            }
+        }
+        OutputFormat::Markdown => {
+            let since = since.unwrap_or(Timestamp::MIN);
+            let schedule = scheduler.get_schedule_since_with(since, midnight_end);
+            // recorded reasons get a column of their own
+            let reasons = scheduler
+                .reasons
+                .is_some()
+                .then(|| reasons_since(scheduler, since, midnight_end));
+            for (day, day_schedule) in schedule {
+                match reasons {
+                    Some(_) => writeln!(
+                        res,
+                        "## {}\n\n| Time | Description | Reason |\n| --- | --- | --- |",
+                        day
+                    )?,
+                    None => writeln!(res, "## {}\n\n| Time | Description |\n| --- | --- |", day)?,
+                }
+                for (time, description) in day_schedule {
+                    write!(res, "| {} | {} |", time, description.replace('|', "\\|"))?;
+                    if let Some(reasons) = &reasons {
+                        let reason = reasons
+                            .get(&format!("{} {}", day, time))
+                            .map_or("", String::as_str);
+                        write!(res, " {} |", reason.replace('|', "\\|"))?;
+                    }
+                    res.push('\n');
+                }
+                res.push('\n');
+            }
+        }
+        OutputFormat::Ics => {
+            let ics_time = |timestamp: Timestamp| timestamp.strftime("%Y%m%dT%H%M%SZ").to_string();
+            res.push_str("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//panini//EN\r\n");
+            for (idx, (description, interval)) in intervals().into_iter().enumerate() {
+                let summary = description
+                    .replace('\\', "\\\\")
+                    .replace(';', "\\;")
+                    .replace(',', "\\,")
+                    .replace('\n', "\\n");
+                write!(
+                    res,
+                    "BEGIN:VEVENT\r\nUID:{}-{}@panini\r\nDTSTAMP:{}\r\nDTSTART:{}\r\n\
+                     DTEND:{}\r\nSUMMARY:{}\r\nEND:VEVENT\r\n",
+                    ics_time(interval.start),
+                    idx,
+                    ics_time(interval.start),
+                    ics_time(interval.start),
+                    ics_time(interval.end),
+                    summary
+                )?;
+            }
+            res.push_str("END:VCALENDAR\r\n");
+        }
+    }
+    output.write_all(res.as_bytes())?;
+    Ok(())
+}
+
+// reads back the events of an ics calendar as written by `write_schedule_as`, i.e. with utc
+// `DTSTART` and `DTEND` and without folded lines
+pub fn read_ics(ics: &str) -> Result<Vec<(String, Interval)>, Box<dyn Error>> {
+    let parse_time = |value: &str| -> Result<Timestamp, Box<dyn Error>> {
+        Ok(DateTime::strptime("%Y%m%dT%H%M%SZ", value)?
+            .to_zoned(TimeZone::UTC)?
+            .timestamp())
+    };
+
+    let mut events = Vec::new();
+    let (mut start, mut end, mut summary) = (None, None, None);
+    for line in ics.lines().map(str::trim_end) {
+        let (name, value) = line.split_once(':').unwrap_or((line, ""));
+        match name {
+            "BEGIN" if value == "VEVENT" => (start, end, summary) = (None, None, None),
+            "DTSTART" => start = Some(parse_time(value)?),
+            "DTEND" => end = Some(parse_time(value)?),
+            "SUMMARY" => summary = Some(unescape_ics_text(value)),
+            "END" if value == "VEVENT" => {
+                let (Some(start), Some(end)) = (start, end) else {
+                    return Err("Expected every event to have a DTSTART and a DTEND".into());
+                };
+                let interval = Interval::normalized(start, end);
+                events.push((summary.take().unwrap_or_default(), interval));
+            }
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+fn unescape_ics_text(value: &str) -> String {
+    let mut res = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => res.push('\n'),
+            Some(escaped) => res.push(escaped),
+            None => res.push('\\'),
+        }
+    }
+    res
+}
+
+// a calendar event that no longer matches the schedule, with where it went unless it was removed
+#[derive(Clone, Debug, PartialEq)]
+pub struct StaleEvent {
+    pub description: String,
+    pub interval: Interval,
+    pub moved_to: Option<Interval>,
+}
+
+impl fmt::Display for StaleEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = |interval: &Interval| {
+            let (start, end) = interval.as_local_pair(&TimeZone::system());
+            format!("{} - {}", start.strftime("%F %R"), end.strftime("%F %R"))
+        };
+        match &self.moved_to {
+            Some(moved_to) => write!(
+                f,
+                "{} at {} moved to {}",
+                self.description,
+                format(&self.interval),
+                format(moved_to)
+            ),
+            None => write!(
+                f,
+                "{} at {} was removed",
+                self.description,
+                format(&self.interval)
+            ),
+        }
+    }
+}
+
+// compares the events of a previously exported calendar with the schedule from `since` on.
+// events without an identical interval in the schedule are stale, and each takes the first new
+// interval of the same description that is not in the calendar as where it moved to
+pub fn stale_events(
+    calendar: &[(String, Interval)],
+    scheduler: &Scheduler,
+    since: Option<Timestamp>,
+) -> Vec<StaleEvent> {
+    let since = since.unwrap_or(Timestamp::MIN);
+    let fresh = scheduler.get_intervals_since(since);
+    let mut added: Vec<_> = fresh
+        .iter()
+        .filter(|event| !calendar.contains(event))
+        .collect();
+
+    calendar
+        .iter()
+        .filter(|(_, interval)| interval.end > since)
+        .filter(|event| !fresh.contains(event))
+        .map(|(description, interval)| {
+            let moved_to = added
+                .iter()
+                .position(|(added_description, _)| added_description == description)
+                .map(|idx| added.remove(idx).1.clone());
+            StaleEvent {
+                description: description.clone(),
+                interval: interval.clone(),
+                moved_to,
+            }
+        })
+        .collect()
+}
+
+// writes the first `count` scheduled intervals ending after `since`, one `%F %R - %R description`
+// line each, with the `DeadlineRisk` marker of tasks at risk and the reason, if recorded. plans are
+// left out unless `show_plans` is set
+pub fn write_top(
+    scheduler: &Scheduler,
+    since: Option<Timestamp>,
+    count: usize,
+    show_plans: bool,
+    midnight_end: MidnightStyle,
+    mut output: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let since = since.unwrap_or(Timestamp::MIN);
+    let mut items: Vec<_> = scheduler
+        .timeline()
+        .map(|(task_idx, interval)| {
+            let description = &scheduler.tasks[task_idx].description;
+            let mut description = match scheduler.deadline_risk(task_idx) {
+                Some(risk) => format!("{} {}", description, risk.marker()),
+                None => description.clone(),
+            };
+            if let Some(reason) = scheduler.reason(task_idx, interval.start) {
+                description = format!("{} ({})", description, reason);
+            }
+            (interval.clone(), description)
+        })
+        .collect();
+    if show_plans {
+        items.extend(
+            scheduler
+                .allocator
+                .plans
+                .iter()
+                .map(|(interval, description)| (interval.clone(), description.clone())),
+        );
+    }
+    items.retain(|(interval, _)| interval.end > since);
+    items.sort();
+
+    for (interval, description) in items.into_iter().take(count) {
+        let (start, end) = interval.as_local_pair(&scheduler.tz);
+        let (end_date, end_time) = midnight_end.display_end(&end);
+        let end = if end_date == start.date() {
+            end_time
+        } else {
+            format!("{} {}", end_date, end_time)
+        };
+        writeln!(
+            output,
+            "{} - {} {}",
+            start.strftime("%F %R"),
+            end,
+            description
+        )?;
+    }
+    Ok(())
+}
+
+// quotes fields with separators, quotes or line breaks
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// writes the schedule to each of `paths` in the format of its extension, `-` being stdout. all
+// formats are checked before anything is written. `midnight_end` overrides the default of each
+// format
+pub fn write_outputs(
+    scheduler: &Scheduler,
+    since: Option<Timestamp>,
+    paths: &[String],
+    midnight_end: Option<MidnightStyle>,
+) -> Result<(), Box<dyn Error>> {
+    let formats = paths
+        .iter()
+        .map(|path| OutputFormat::from_path(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (path, format) in paths.iter().zip(formats) {
+        let midnight_end = midnight_end.unwrap_or(format.default_midnight_end());
+        if path == "-" {
+            write_schedule_with(scheduler, since, format, midnight_end, io::stdout())?;
+        } else {
+            write_schedule_with(scheduler, since, format, midnight_end, File::create(path)?)?;
+        }
+    }
+    Ok(())
+}
+
+// timing of one scheduling run over a synthetic problem, see `benchmark`
+#[derive(Clone, Debug)]
+pub struct BenchmarkReport {
+    pub tasks: usize,
+    pub days: i64,
+    pub steps: usize,
+    pub elapsed: Duration,
+}
+
+impl BenchmarkReport {
+    pub fn steps_per_sec(&self) -> f64 {
+        self.steps as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} tasks over {} days: {} steps in {:.3}s, {:.0} steps/sec",
+            self.tasks,
+            self.days,
+            self.steps,
+            self.elapsed.as_secs_f64(),
+            self.steps_per_sec()
+        )
+    }
+}
+
+// schedules `task_count` generated tasks in chains of four with the default heuristics, over as
+// many days as they need at sixteen free hours a day. only the scheduling itself is timed
+pub fn benchmark(task_count: usize) -> Result<BenchmarkReport, Box<dyn Error>> {
+    let start: Timestamp = "2025-03-03T00:00Z".parse()?;
+    let days = task_count as i64 * 2 / 16 + 1;
+    let start_date = start.to_zoned(TimeZone::system()).date();
+
+    let mut config = String::from("tasks:\n");
+    for idx in 0..task_count {
+        if idx % 4 == 0 {
+            config.push_str("  -\n");
+        }
+        let deadline =
+            start_date.checked_add(Span::new().days(1 + idx as i64 * days / task_count as i64))?;
+        writeln!(
+            config,
+            "    - Task {} / {} / {}h / 0% / {}",
+            idx,
+            deadline,
+            1 + idx % 3,
+            "!".repeat(1 + idx % 3)
+        )?;
+    }
+    let end = start_date.checked_add(Span::new().days(days))?;
+    writeln!(
+        config,
+        "plans:\n  \"* * *\":\n    00:00-08:00: Sleep\ngranularity: 1h\nstart: {} 00:00\nend: {} 00:00",
+        start_date, end
+    )?;
+
+    let mut scheduler = load_with_clock(config.as_bytes(), &FixedClock(start))?;
+    let timer = Instant::now();
+    let mut steps = 0;
+    while let Some((task_idx, task_interval)) = scheduler.next_block() {
+        scheduler.schedule_task(task_idx, task_interval);
+        steps += 1;
+    }
+    scheduler.fill_stretch();
+
+    Ok(BenchmarkReport {
+        tasks: task_count,
+        days,
+        steps,
+        elapsed: timer.elapsed(),
+    })
+}
+
+// `parse_timestamp_with_clock` against the system clock
+pub fn parse_timestamp(value: &str) -> Result<Timestamp, Box<dyn Error>> {
+    parse_timestamp_with_clock(value, &SystemClock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{scheduler::StartRounding, tests::get_test_scheduler};
+    use jiff::ToSpan;
+    use std::mem;
+
+    #[test]
+    fn test_run_from_reader() {
+        let config = r#"
+tasks:
+  - - Task / 2025-03-06 / 2h / 0%
+plans:
+  "* * *":
+    00:00-09:00: Sleep
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-06 00:00
+"#;
+        let mut output = Vec::new();
+
+        run(config.as_bytes(), &mut output).unwrap();
+
+        let schedule: Schedule = serde_yaml::from_slice(&output).unwrap();
+        assert_eq!(schedule["2025-03-05"]["00:00 - 09:00"], "Sleep");
+        assert_eq!(schedule["2025-03-05"]["09:00 - 11:00"], "Task");
+    }
+
+    #[test]
+    fn test_config_errors() {
+        let config = r#"
+tasks:
+  - - Task / 2025-03-06 / 2h / 0%
+plans: {}
+start: 2025-03-05 00:00
+end: 2025-03-06 00:00
+"#;
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(err.contains("granularity"), "{}", err);
+
+        let config = r#"
+tasks:
+  - - Task / 2025-03-06 / 2h / 0%
+    - [Task]
+plans: {}
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-06 00:00
+"#;
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(err.contains("tasks[0][1]"), "{}", err);
+        assert!(err.contains("   4 |     - [Task]"), "{}", err);
+
+        let config = r#"
+tasks:
+  - - Task / 2025-03-06 / 2h / 0%
+  - - Task / 2025-03-06 / 2h
+plans: {}
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-06 00:00
+"#;
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(
+            err.contains("tasks: [1][0] 'Task / 2025-03-06 / 2h'"),
+            "{}",
+            err
+        );
+
+        let config = r#"
+tasks:
+  - - Stale task / 2025-03-01 / 2h / 0%
+plans: {}
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-06 00:00
+strict: true
+"#;
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(
+            err.contains("Stale task is due at 2025-03-01 00:00"),
+            "{}",
+            err
+        );
+        assert!(load(config.replace("strict: true", "").as_bytes()).is_ok());
+
+        let config = r#"
+tasks: []
+plans: {}
+granularity: 1h
+start: 2025-03-06 00:00
+end: 2025-03-05 00:00
+"#;
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(
+            err.contains("end: Expected the end to be after the start"),
+            "{}",
+            err
+        );
+
+        let config = r#"
+tasks:
+  - - Task / 2025-03-06 / 2h / 0%
+plans: {}
+pins:
+  "2025-03-05 12:00-11:00": Task
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-06 00:00
+"#;
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(
+            err.contains("pins: Expected the interval to end after it starts"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_daily_volume() {
+        let config = r#"
+tasks:
+  - - Maintenance / 2025-03-10 / 1h/day / 0%
+plans: {}
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-12 00:00
+"#;
+        let mut scheduler = load(config.as_bytes()).unwrap();
+        assert_eq!(scheduler.tasks[0].volume, 5.0);
+
+        scheduler.schedule();
+        let schedule = Schedule::from(&scheduler);
+        assert_eq!(schedule.len(), 5);
+        for day in 5..10 {
+            let day = &schedule[&format!("2025-03-{:02}", day)];
+            assert_eq!(day.len(), 1);
+            assert!(day.keys().all(|interval| interval.ends_with(":00")));
+        }
+        assert_eq!(scheduler.get_total_task_hours(0), 5.0);
+    }
+
+    #[test]
+    fn test_ideal_lead() {
+        let config = |paper_volume| {
+            format!(
+                r#"
+tasks:
+  - - Paper / 2025-03-07 / {}h / 0% / lead=1d
+  - - Other / 2025-03-07 / 14h / 0%
+plans:
+  "* * *":
+    00:00-08:00: Sleep
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-07 00:00
+"#,
+                paper_volume
+            )
+        };
+        let ideal_deadline = parse_timestamp("2025-03-06 00:00").unwrap();
+        let last_end = |scheduler: &Scheduler, idx: usize| {
+            scheduler[idx]
+                .iter()
+                .map(|interval| interval.end)
+                .max()
+                .unwrap()
+        };
+
+        // the paper finishes a day early, ahead of the bigger task due at the same time
+        let mut scheduler = load(config(4).as_bytes()).unwrap();
+        scheduler.schedule();
+        assert_eq!(scheduler.get_total_task_hours(0), 4.0);
+        assert!(last_end(&scheduler, 0) <= ideal_deadline);
+        assert!(last_end(&scheduler, 1) > ideal_deadline);
+
+        // too much work to finish early, so it spills into the lead
+        let mut scheduler = load(config(20).as_bytes()).unwrap();
+        scheduler.schedule();
+        assert_eq!(scheduler.get_total_task_hours(0), 20.0);
+        assert!(last_end(&scheduler, 0) > ideal_deadline);
+        assert!(last_end(&scheduler, 0) <= scheduler.tasks[0].deadline);
+    }
+
+    #[test]
+    fn test_resources() {
+        let config = r#"
+tasks:
+  - - Logo / 2025-03-07 / 4h / 0% / requires=design
+  - - Report / 2025-03-07 / 4h / 0%
+  - - Poster / 2025-03-07 / 2h / 0% / requires=design
+    - Print poster / 2025-03-07 / 1h / 0%
+plans:
+  "* * *":
+    00:00-09:00: Sleep
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-07 00:00
+resources:
+  - name: Alice
+    skills: [design]
+  - name: Bob
+    plans:
+      "* * *":
+        09:00-10:00: Standup
+"#;
+        let mut team = load_team(config.as_bytes(), &SystemClock).unwrap();
+        let descriptions = |scheduler: &Scheduler| {
+            scheduler
+                .tasks
+                .iter()
+                .map(|task| task.description.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(team[0].0, "Alice");
+        assert_eq!(descriptions(&team[0].1), ["Logo", "Poster", "Print poster"]);
+        assert_eq!(team[1].0, "Bob");
+        assert_eq!(descriptions(&team[1].1), ["Report"]);
+        // the chain keeps its dependency after being split off
+        assert_eq!(team[0].1.tasks[2].dependencies, [1]);
+
+        for (_, scheduler) in &mut team {
+            scheduler.schedule();
+        }
+        let bob = Schedule::from(&team[1].1);
+        assert_eq!(bob["2025-03-05"]["09:00 - 10:00"], "Standup");
+        assert_eq!(bob["2025-03-05"]["10:00 - 14:00"], "Report");
+        assert_eq!(
+            Schedule::from(&team[0].1)["2025-03-05"]["09:00 - 13:00"],
+            "Logo"
+        );
+
+        let config = config.replace("skills: [design]", "skills: []");
+        let err = load_team(config.as_bytes(), &SystemClock).err().unwrap();
+        assert!(err.to_string().contains("needed by Logo"), "{}", err);
+    }
+
+    #[test]
+    fn test_commit_horizon() {
+        let config = r#"
+tasks:
+  - - Thesis / 2025-05-07 / 200h / 0%
+  - - Slides / 2025-05-07 / 4h / 0% / stretch=true
+plans:
+  "* * *":
+    00:00-08:00: Sleep
+granularity: 1h
+start: 2025-04-07 00:00
+end: 2025-05-08 00:00
+commit_horizon: 7d
+"#;
+        let mut scheduler = load(config.as_bytes()).unwrap();
+        scheduler.schedule();
+
+        // the horizon is 168 hours, which only ends at midnight without a clock change in between
+        let commit_end = parse_timestamp("2025-04-14 00:00").unwrap();
+        assert_eq!(scheduler.commit_end(), commit_end);
+        assert!(scheduler
+            .timeline()
+            .all(|(_, interval)| interval.end <= commit_end));
+        // the thesis keeps the rest of its work for later runs
+        assert_eq!(scheduler.get_total_task_hours(0), 7.0 * 16.0);
+        assert!(scheduler.get_total_task_hours(0) < scheduler.tasks[0].volume);
+    }
+
+    #[test]
+    fn test_weekly_template() {
+        let config = r#"
+tasks: []
+plans: {}
+weekly_template:
+  Standup: weekdays 09:30 / 15m
+  Gym: monday,wednesday,friday 18:00 / 1h
+granularity: 1h
+start: 2025-03-03 00:00
+end: 2025-03-10 00:00
+"#;
+        let schedule = Schedule::from(&load(config.as_bytes()).unwrap());
+
+        for day in 3..8 {
+            let day = &schedule[&format!("2025-03-{:02}", day)];
+            assert_eq!(day["09:30 - 09:45"], "Standup");
+        }
+        assert!(!schedule.contains_key("2025-03-08"));
+        assert!(!schedule.contains_key("2025-03-09"));
+        assert_eq!(schedule["2025-03-05"]["18:00 - 19:00"], "Gym");
+        assert!(!schedule["2025-03-04"].contains_key("18:00 - 19:00"));
+    }
+
+    #[test]
+    fn test_break() {
+        let config = r#"
+tasks:
+  - - Report / 2025-03-06 / 2h / 0%
+plans: {}
+break: 15m
+granularity: 1h
+start: 2025-03-05 09:00
+end: 2025-03-06 00:00
+"#;
+        let mut scheduler = load(config.as_bytes()).unwrap();
+        assert_eq!(scheduler.allocator.break_span.get_minutes(), 15);
+        scheduler.schedule();
+        let schedule = Schedule::from(&scheduler);
+        assert_eq!(schedule["2025-03-05"]["09:00 - 10:00"], "Report");
+        assert_eq!(schedule["2025-03-05"]["10:15 - 11:15"], "Report");
+
+        let config = config.replace("break: 15m", "break: soon");
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(err.contains("break"), "{}", err);
+    }
+
+    #[test]
+    fn test_working_hours() {
+        let config = r#"
+tasks:
+  - - Report / 2025-03-07 / 6h / 0%
+plans: {}
+working_hours: 09:00-13:00
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-07 00:00
+"#;
+        let mut scheduler = load(config.as_bytes()).unwrap();
+        scheduler.schedule();
+        let schedule = Schedule::from(&scheduler);
+        assert_eq!(schedule["2025-03-05"]["09:00 - 13:00"], "Report");
+        assert_eq!(schedule["2025-03-06"]["09:00 - 11:00"], "Report");
+        assert_eq!(schedule.values().map(|day| day.len()).sum::<usize>(), 2);
+
+        // night shifts run past midnight
+        let night = config.replace("09:00-13:00", "22:00-02:00");
+        let mut scheduler = load(night.as_bytes()).unwrap();
+        scheduler.schedule();
+        let schedule = Schedule::from(&scheduler);
+        assert_eq!(schedule["2025-03-05"]["00:00 - 02:00"], "Report");
+        assert_eq!(schedule["2025-03-05"]["22:00 - 02:00"], "Report");
+
+        let config = config.replace("09:00-13:00", "09:00-09:00");
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(err.contains("working_hours"), "{}", err);
+    }
+
+    #[test]
+    fn test_milestones() {
+        let config = r#"
+tasks:
+  - - Integration / 2025-03-08 / 2h / 0% / after=api_freeze
+  - - Client / 2025-03-08 / 2h / 0% / after=api_freeze
+  - - Docs / 2025-03-08 / 1h / 0%
+milestones:
+  api_freeze: 2025-03-06 12:00
+plans: {}
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-08 00:00
+"#;
+        let mut scheduler = load(config.as_bytes()).unwrap();
+        let api_freeze = scheduler.tasks[0].not_before.unwrap();
+        assert_eq!(scheduler.tasks[1].not_before, Some(api_freeze));
+        assert_eq!(scheduler.tasks[2].not_before, None);
+
+        scheduler.schedule();
+        // nothing else is left to do before the freeze, so both tasks start right at it
+        assert_eq!(
+            scheduler[2],
+            [Interval::from_span(scheduler.interval.start, 1.hour())]
+        );
+        for task_idx in 0..2 {
+            assert!(scheduler[task_idx]
+                .iter()
+                .all(|interval| interval.start >= api_freeze));
+            assert_eq!(scheduler.get_total_task_hours(task_idx), 2.0);
+        }
+        assert_eq!(
+            scheduler
+                .timeline()
+                .find(|(idx, _)| *idx != 2)
+                .map(|(_, interval)| interval.start),
+            Some(api_freeze)
+        );
+
+        let config = config.replace("api_freeze: ", "api_thaw: ");
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(err.contains("Unknown milestone: api_freeze"), "{}", err);
+    }
+
+    #[test]
+    fn test_priority_cap() {
+        let config = r#"
+tasks:
+  - - Urgent / 2025-03-06 / 2h / 0% / p=5
+  - - Fine / 2025-03-06 / 2h / 0% / p=1.5
+plans: {}
+priority_cap: 3
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-06 00:00
+"#;
+        let scheduler = load(config.as_bytes()).unwrap();
+        assert_eq!(scheduler.tasks[0].priority, 5.0);
+        assert_eq!(heuristics::priority(&scheduler, 0), 3.0);
+        assert_eq!(heuristics::priority(&scheduler, 1), 1.5);
+        // same deadline, so the urgency differs by the capped priority alone
+        let weighted_urgency = heuristics::weighted_urgency();
+        assert_eq!(
+            weighted_urgency(&scheduler, 0),
+            2.0 * weighted_urgency(&scheduler, 1)
+        );
+        // clamping is not a problem, so strict configs still load
+        assert_eq!(scheduler.validate(), Vec::<String>::new());
+        let messages: Vec<_> = scheduler
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        assert_eq!(
+            messages,
+            ["Task Urgent has priority 5, counted as the cap of 3"]
+        );
+        let strict = config.replace("priority_cap: 3", "priority_cap: 3\nstrict: true");
+        assert!(load(strict.as_bytes()).is_ok());
+
+        let config = config.replace("priority_cap: 3", "priority_cap: 0");
+        let err = load(config.as_bytes()).err().unwrap().to_string();
+        assert!(err.contains("priority_cap"), "{}", err);
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut scheduler = get_test_scheduler();
+        let tasks = scheduler.tasks.clone();
+        let plans = scheduler.allocator.plans.clone();
+        let granularity = scheduler.allocator.granularity;
+        let interval = scheduler.interval.clone();
+        let heuristics = mem::take(&mut scheduler.heuristics)
+            .into_iter()
+            .map(|(_, heuristic, _)| heuristic)
+            .collect();
+
+        let schedule = plan(tasks, plans.into(), interval, heuristics, granularity).unwrap();
+
+        // `plan` lays the schedule out in the system time zone
+        let mut expected = get_test_scheduler().with_time_zone(TimeZone::system());
+        while let Some((task_idx, task_interval)) = expected.next_block() {
+            expected.schedule_task(task_idx, task_interval);
+        }
+        assert_eq!(schedule, Schedule::from(&expected));
+
+        let interval = expected.interval.clone();
+        assert!(plan(
+            Vec::new(),
+            Plans::default(),
+            interval,
+            Vec::new(),
+            Span::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_round_start() {
+        let config = r#"
+tasks: []
+plans: {}
+granularity: 1h
+start: 2025-03-05 14:30
+end: 2025-03-06 00:00
+round_start: hour
+"#;
+        let scheduler = load(config.as_bytes()).unwrap();
+        assert_eq!(
+            scheduler.interval.start,
+            parse_timestamp("2025-03-05 15:00").unwrap()
+        );
+
+        let scheduler = load(config.replace("round_start: hour", "").as_bytes()).unwrap();
+        assert_eq!(
+            scheduler.interval.start,
+            parse_timestamp("2025-03-05 14:30").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_team_from_config_overrides() {
+        let config = r#"
+tasks: []
+plans: {}
+granularity: 1h
+start: 2025-03-05 09:30
+end: 2025-03-06 00:00
+round_start: day
+lunch: 12:00 / 1h
+"#;
+        let config = SchedulerConfig::from_yaml(config)
+            .unwrap()
+            .with_round_start(StartRounding::Hour)
+            .with_lunch("12:30 / 30m".to_string());
+        let (_, scheduler) = team_from_config(config, &SystemClock).unwrap().remove(0);
+
+        assert_eq!(
+            scheduler.interval.start,
+            parse_timestamp("2025-03-05 10:00").unwrap()
+        );
+        let lunch = Interval::new(
+            parse_timestamp("2025-03-05 12:30").unwrap(),
+            parse_timestamp("2025-03-05 13:00").unwrap(),
+        );
+        assert_eq!(
+            scheduler
+                .allocator
+                .plans
+                .iter()
+                .map(|(plan, description)| (plan.clone(), description.as_str()))
+                .collect::<Vec<_>>(),
+            [(lunch, "Lunch")]
+        );
+    }
+
+    #[test]
+    fn test_min_notice_from_now() {
+        let config = r#"
+tasks:
+  - - Task / 2025-03-06 / 2h / 0%
+plans: {}
+granularity: 1h
+start: now
+end: 2025-03-06 00:00
+min_notice: 2h
+"#;
+        let now = parse_timestamp("2025-03-05 09:30").unwrap();
+        let clock = FixedClock(now);
+        assert_eq!(parse_timestamp_with_clock("now", &clock).unwrap(), now);
+
+        let mut scheduler = load_with_clock(config.as_bytes(), &clock).unwrap();
+        scheduler.schedule();
+        assert_eq!(scheduler.interval.start, now);
+        assert_eq!(
+            scheduler[0],
+            vec![Interval::from_span(now + 2.hours(), 2.hours())]
+        );
+    }
+
+    #[test]
+    fn test_export_tasks_with_computed_fields() {
+        let config = r#"
+tasks:
+  - - Draft / 2025-03-10 / 4h / 50% / every=daily
+    - Review / 2025-03-10 / 1h / 0%
+plans: {}
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-07 00:00
+"#;
+        let scheduler = load(config.as_bytes()).unwrap();
+        let tasks = scheduler.export_tasks_with_computed_fields();
+
+        // the cadence moves the first draft's deadline to the next day and adds a second one
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].deadline, "2025-03-06 00:00");
+        assert_eq!(tasks[0].remaining_volume, 2.0);
+        assert_eq!(tasks[1].dependencies, vec!["Draft"]);
+        assert_eq!(tasks[2].deadline, "2025-03-07 00:00");
+
+        let yaml = serde_yaml::to_string(&tasks).unwrap();
+        assert!(yaml.contains("remaining_volume: 2.0"), "{}", yaml);
+    }
+
+    #[test]
+    fn test_write_outputs() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.schedule();
+        let dir = std::env::temp_dir().join(format!("panini-outputs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = ["schedule.yaml", "schedule.json"]
+            .map(|name| dir.join(name).to_str().unwrap().to_string());
+
+        write_outputs(&scheduler, None, &paths, Some(MidnightStyle::As24)).unwrap();
+
+        // json reads back as yaml, once both show midnight the same way
+        let [yaml, json] = paths.clone().map(|path| {
+            serde_yaml::from_str::<Schedule>(&std::fs::read_to_string(path).unwrap()).unwrap()
+        });
+        assert_eq!(yaml, json);
+        assert_eq!(yaml["2025-03-05"]["09:00 - 11:00"], "Task 2");
+        assert!(std::fs::read_to_string(&paths[1]).unwrap().starts_with('{'));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let err = write_outputs(&scheduler, None, &["schedule.txt".to_string()], None)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains(".yaml, .json"), "{}", err);
+    }
+
+    #[test]
+    fn test_write_schedule_formats() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.tasks[2].description = "Task 2, \"draft\"".to_string();
+        scheduler.schedule();
+        let write = |format| {
+            let mut output = Vec::new();
+            write_schedule_as(&scheduler, None, format, &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        let csv = write(OutputFormat::Csv);
+        assert!(
+            csv.contains("2025-03-05 09:00,2025-03-05 11:00,\"Task 2, \"\"draft\"\"\"\n"),
+            "{}",
+            csv
+        );
+        let markdown = write(OutputFormat::Markdown);
+        assert!(
+            markdown.contains("| 09:00 - 11:00 | Task 2, \"draft\" |"),
+            "{}",
+            markdown
+        );
+        let ics = write(OutputFormat::Ics);
+        assert!(
+            ics.contains(
+                "DTSTART:20250305T090000Z\r\nDTEND:20250305T110000Z\r\nSUMMARY:Task 2\\, \"draft\""
+            ),
+            "{}",
+            ics
+        );
+    }
+
+    #[test]
+    fn test_midnight_end() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.schedule();
+        let write = |format, midnight_end| {
+            let mut output = Vec::new();
+            match midnight_end {
+                Some(midnight_end) => {
+                    write_schedule_with(&scheduler, None, format, midnight_end, &mut output)
+                }
+                None => write_schedule_as(&scheduler, None, format, &mut output),
+            }
+            .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        for (format, default, overridden) in [
+            (OutputFormat::Yaml, "22:00 - 24:00", "22:00 - 00:00"),
+            (
+                OutputFormat::Markdown,
+                "| 22:00 - 24:00 |",
+                "| 22:00 - 00:00 |",
+            ),
+            (OutputFormat::Json, "\"22:00 - 00:00\"", "\"22:00 - 24:00\""),
+            (
+                OutputFormat::Csv,
+                "2025-03-05 22:00,2025-03-06 00:00,",
+                "2025-03-05 22:00,2025-03-05 24:00,",
+            ),
+        ] {
+            let output = write(format, None);
+            assert!(output.contains(default), "{:?}: {}", format, output);
+            let style = match format.default_midnight_end() {
+                MidnightStyle::Keep => MidnightStyle::As24,
+                MidnightStyle::As24 => MidnightStyle::Keep,
+            };
+            let output = write(format, Some(style));
+            assert!(output.contains(overridden), "{:?}: {}", format, output);
+        }
+    }
+
+    #[test]
+    fn test_stale_events() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.tasks[2].description = "Task 2, \"draft\"".to_string();
+        scheduler.schedule();
+        let mut ics = Vec::new();
+        write_schedule_as(&scheduler, None, OutputFormat::Ics, &mut ics).unwrap();
+        let calendar = read_ics(&String::from_utf8(ics).unwrap()).unwrap();
+        assert!(stale_events(&calendar, &scheduler, None).is_empty());
+
+        // a new plan pushes task 2 back by an hour
+        let mut rescheduled = get_test_scheduler();
+        rescheduled.tasks[2].description = "Task 2, \"draft\"".to_string();
+        rescheduled.allocator.plans.insert(
+            Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 1.hour()),
+            "Meeting".into(),
+        );
+        rescheduled.schedule();
+
+        let stale = stale_events(&calendar, &rescheduled, None);
+        let moved = stale
+            .iter()
+            .find(|event| event.interval.start == "2025-03-05T09:00Z".parse().unwrap())
+            .unwrap();
+        assert_eq!(moved.description, "Task 2, \"draft\"");
+        assert_eq!(
+            moved.moved_to.as_ref().unwrap().start,
+            "2025-03-05T10:00Z".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let mut scheduler = get_test_scheduler().with_reasons();
+        scheduler.schedule();
+
+        let mut output = Vec::new();
+        write_schedule_as(&scheduler, None, OutputFormat::Json, &mut output).unwrap();
+        let schedule: Schedule = serde_yaml::from_slice(&output).unwrap();
+        assert_eq!(
+            schedule["Reasons"]["2025-03-05 11:00 - 12:00"],
+            "placed here to meet its deadline"
+        );
+        assert_eq!(schedule["Reasons"].len(), scheduler.timeline().count());
+        assert_eq!(
+            schedule["Reasons"]["2025-03-05 15:00 - 16:00"],
+            "the most work left, first free slot"
+        );
+
+        // the default heuristics all have names of their own
+        let config = r#"
+tasks:
+  - - Urgent / 2025-03-06 / 2h / 0%
+  - - Later / 2025-03-09 / 2h / 0%
+plans: {}
+granularity: 1h
+start: 2025-03-05 00:00
+end: 2025-03-06 00:00
+"#;
+        let mut urgent = load(config.as_bytes()).unwrap().with_reasons();
+        urgent.schedule();
+        let reasons: Vec<_> = urgent
+            .timeline()
+            .filter_map(|(task_idx, interval)| urgent.reason(task_idx, interval.start))
+            .collect();
+        // `lead_deadline` favours the urgent task
+        assert_eq!(
+            reasons,
+            [
+                "placed here to meet its deadline",
+                "the only task that could run"
+            ]
+        );
+        assert!(urgent
+            .heuristics
+            .iter()
+            .any(|(name, _, _)| name == "lead_deadline"));
+
+        let mut output = Vec::new();
+        write_schedule_as(&scheduler, None, OutputFormat::Markdown, &mut output).unwrap();
+        let markdown = String::from_utf8(output).unwrap();
+        assert!(markdown.contains("| 11:00 - 12:00 | Task 0 | placed here to meet its deadline |"));
+        assert!(markdown.contains("| 00:00 - 09:00 |  |  |"), "{}", markdown);
+
+        let mut output = Vec::new();
+        write_top(&scheduler, None, 2, false, MidnightStyle::As24, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "2025-03-05 09:00 - 11:00 Task 2 (higher priority than the other tasks, first free \
+             slot)\n2025-03-05 11:00 - 12:00 Task 0 [red] (placed here to meet its deadline)\n"
+        );
+    }
+
+    #[test]
+    fn test_write_top() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.schedule();
+        let top = |scheduler: &Scheduler, count, show_plans| {
+            let mut output = Vec::new();
+            write_top(
+                scheduler,
+                None,
+                count,
+                show_plans,
+                MidnightStyle::As24,
+                &mut output,
+            )
+            .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_eq!(
+            top(&scheduler, 3, false),
+            "2025-03-05 09:00 - 11:00 Task 2\n\
+             2025-03-05 11:00 - 12:00 Task 0 [red]\n\
+             2025-03-05 12:00 - 13:00 Task 2\n"
+        );
+
+        // task 2 finishes right on its deadline
+        scheduler.deadline_warning = 1.hour();
+        assert_eq!(
+            top(&scheduler, 3, false),
+            "2025-03-05 09:00 - 11:00 Task 2 [amber]\n\
+             2025-03-05 11:00 - 12:00 Task 0 [red]\n\
+             2025-03-05 12:00 - 13:00 Task 2 [amber]\n"
+        );
+        assert_eq!(
+            top(&scheduler, 2, true),
+            "2025-03-05 00:00 - 09:00 \n2025-03-05 09:00 - 11:00 Task 2 [amber]\n"
+        );
+    }
+
+    #[test]
+    fn test_write_schedule_since() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.schedule();
+        let mut output = Vec::new();
+
+        let since = parse_timestamp("2025-03-05T12:30Z").unwrap();
+        write_schedule(&scheduler, Some(since), &mut output).unwrap();
+
+        let schedule: Schedule = serde_yaml::from_slice(&output).unwrap();
+        let day = &schedule["2025-03-05"];
+        assert!(!day.contains_key("09:00 - 11:00"));
+        assert!(!day.contains_key("00:00 - 09:00"));
+        assert_eq!(day["12:30 - 13:00"], "Task 2");
+        assert_eq!(day["15:00 - 16:00"], "Task 3");
+    }
+
+    #[test]
+    fn test_unscheduled_section() {
+        let mut scheduler = get_test_scheduler();
+        scheduler.schedule();
+        let mut output = Vec::new();
+
+        write_schedule(&scheduler, None, &mut output).unwrap();
+
+        let schedule: Schedule = serde_yaml::from_slice(&output).unwrap();
+        let unscheduled = &schedule["Unscheduled"];
+        assert_eq!(unscheduled["Zero priority task"], "zero priority");
+        assert!(!unscheduled.contains_key("Empty task"));
+        assert_eq!(unscheduled.len(), 1);
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let report = benchmark(100).unwrap();
+
+        assert_eq!(report.tasks, 100);
+        assert!(report.steps > 0);
+        assert!(report.elapsed < Duration::from_secs(120), "{}", report);
+    }
+}
@@ -1,114 +1,694 @@
 use crate::{
-    allocators::{Plans, TaskAllocatorWithPlans},
+    allocators::{default_time_aliases, DailyCapacity, PlanKind, Plans, TaskAllocatorWithPlans},
+    clock::{Clock, SystemClock},
     group_by::GroupBy,
-    heuristics::Heuristic,
+    heuristics::{self, Heuristic},
     interval::Interval,
-    tasks::{Task, TaskIdx, Tasks},
+    parse::{parse_span, parse_time_window, parse_timestamp_with_clock},
+    solar::Coordinates,
+    tasks::{Task, TaskIdx, Tasks, WindowEnd},
 };
-use derive_more::{Deref, DerefMut};
+use derive_more::Deref;
 use indexmap::IndexMap;
-use jiff::{civil::DateTime, tz::TimeZone, RoundMode, Span, Timestamp, Unit, ZonedRound};
-use serde::Deserialize;
-use std::{cmp::Ordering, collections::BTreeMap, error::Error};
+use jiff::{
+    civil::{Date, DateTime, Time, Weekday},
+    tz::TimeZone,
+    RoundMode, SignedDuration, Span, Timestamp, ToSpan, Unit, Zoned,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt::Display,
+    mem,
+};
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct SchedulerConfig {
     tasks: Vec<Vec<String>>,
     plans: IndexMap<String, IndexMap<String, String>>,
+    // available contexts in the same format as plans
+    #[serde(default)]
+    contexts: IndexMap<String, IndexMap<String, String>>,
+    // named time ranges, e.g. `morning: 09:00-12:00`, overriding the defaults
+    #[serde(default)]
+    time_aliases: IndexMap<String, String>,
+    // where `sunrise` and `sunset` in plans are computed for
+    coordinates: Option<Coordinates>,
     granularity: String,
+    // `%F %R` or `now`
     start: String,
+    #[serde(default)]
+    round_start: StartRounding,
     end: String,
+    week_start: Option<String>,
+    min_notice: Option<String>,
+    // gaps shorter than this are left idle, except for the last bit of a task
+    min_session: Option<String>,
+    // `%R-%R` of each day tasks may be scheduled in, e.g. `09:00-17:00` or `22:00-06:00` for nights
+    working_hours: Option<String>,
+    // free time kept between two work blocks, e.g. `10m`
+    #[serde(rename = "break")]
+    break_span: Option<String>,
+    // descriptions of plans that do not count as taken time for deadlines
+    #[serde(default)]
+    soft_plans: BTreeSet<String>,
+    // tasks with less free time than this before their deadline are dropped
+    drop_below: Option<String>,
+    max_tasks_per_day: Option<usize>,
+    // priorities above this count as this much, so one task cannot outweigh every other heuristic
+    priority_cap: Option<f32>,
+    // tasks finishing closer than this to their deadline are flagged as at risk
+    deadline_warning: Option<String>,
+    // `%F %R` or `now`, nothing is allocated or moved before it
+    frozen_until: Option<String>,
+    // only this much of the interval from its start is scheduled, the rest is left for later runs
+    commit_horizon: Option<String>,
+    // named dates tasks can wait on with `after=name`, `%F %R` or `now`, e.g.
+    // `api_freeze: 2025-03-12 00:00`
+    #[serde(default)]
+    milestones: IndexMap<String, String>,
+    // daily lunch plan in the form `%R / span`, e.g. `12:30 / 1h`
+    lunch: Option<String>,
+    // recurring plans by description in the form `days %R / span`, where days are `daily`,
+    // `weekdays`, `weekends` or a comma separated list, e.g. `monday,friday 18:00 / 1h`
+    #[serde(default)]
+    weekly_template: IndexMap<String, String>,
+    // daily review task pinned in the form `%R / span`, e.g. `17:45 / 15m`
+    review: Option<String>,
+    #[serde(default)]
+    pins: IndexMap<String, String>,
+    #[serde(default)]
+    pin_conflict: PinConflict,
+    // turns problems found by `Scheduler::validate` into errors
+    #[serde(default)]
+    strict: bool,
+    // people or machines the tasks are shared between, see `into_team`
+    #[serde(default)]
+    resources: Vec<Resource>,
+}
+
+// someone tasks can be assigned to, with plans of their own on top of the shared ones
+#[derive(Clone, Debug, Deserialize)]
+pub struct Resource {
+    pub name: String,
+    #[serde(default)]
+    pub skills: Vec<String>,
+    #[serde(default)]
+    pub plans: IndexMap<String, IndexMap<String, String>>,
+}
+
+// what happens to the plans a pinned task overlaps
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PinConflict {
+    // overlapping plans are removed or split around the pin
+    #[default]
+    PinWins,
+    // the pin is clipped to the time left free by the plans
+    PlanWins,
+    // overlapping a plan is an error
+    Error,
+}
+
+// how the start of the scheduling interval is rounded, e.g. when it is `now`
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartRounding {
+    #[default]
+    None,
+    // up to the next full hour
+    Hour,
+    // down to local midnight
+    Day,
+}
+
+impl StartRounding {
+    pub fn round(self, timestamp: Timestamp, tz: &TimeZone) -> Timestamp {
+        let (unit, mode) = match self {
+            StartRounding::None => return timestamp,
+            StartRounding::Hour => (Unit::Hour, RoundMode::Ceil),
+            StartRounding::Day => (Unit::Day, RoundMode::Trunc),
+        };
+        Interval::new(timestamp, timestamp).round_start(unit, mode, tz)
+    }
+}
+
+fn parse_pin_interval(value: &str) -> Result<Interval, Box<dyn Error>> {
+    let invalid_pin = || format!("Expected pin in the form '%F %R-%R', got: {}", value);
+    let (date, time) = value.trim().split_once(' ').ok_or_else(invalid_pin)?;
+    let (start, end) = time.split_once('-').ok_or_else(invalid_pin)?;
+    let [start, end] = [start, end].map(|time| {
+        DateTime::strptime("%F %R", format!("{} {}", date, time.trim()))?
+            .to_zoned(TimeZone::system())
+            .map(|zoned| zoned.timestamp())
+    });
+
+    Interval::try_new(start?, end?)
+}
+
+// parses a weekly template entry in the form `days %R / span`
+fn parse_weekly(value: &str) -> Result<(Vec<Weekday>, Time, Span), Box<dyn Error>> {
+    let (days, daily) = value
+        .trim()
+        .split_once(' ')
+        .ok_or_else(|| format!("Expected the form 'days %R / span', got: {}", value))?;
+    let weekdays = match days {
+        "daily" => vec![
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+            Weekday::Saturday,
+            Weekday::Sunday,
+        ],
+        "weekdays" => vec![
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+        ],
+        "weekends" => vec![Weekday::Saturday, Weekday::Sunday],
+        days => days
+            .split(',')
+            .map(parse_weekday)
+            .collect::<Result<_, _>>()?,
+    };
+    let (time, span) = parse_daily(daily)?;
+    Ok((weekdays, time, span))
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday, Box<dyn Error>> {
+    match value.to_lowercase().as_str() {
+        "monday" => Ok(Weekday::Monday),
+        "tuesday" => Ok(Weekday::Tuesday),
+        "wednesday" => Ok(Weekday::Wednesday),
+        "thursday" => Ok(Weekday::Thursday),
+        "friday" => Ok(Weekday::Friday),
+        "saturday" => Ok(Weekday::Saturday),
+        "sunday" => Ok(Weekday::Sunday),
+        _ => Err(format!("Invalid weekday: {}", value).into()),
+    }
+}
+
+impl SchedulerConfig {
+    // replaces the daily lunch plan, in the form `%R / span`
+    pub fn with_lunch(mut self, lunch: String) -> Self {
+        self.lunch = Some(lunch);
+        self
+    }
+
+    pub fn with_round_start(mut self, round_start: StartRounding) -> Self {
+        self.round_start = round_start;
+        self
+    }
+
+    // deserializes the config, quoting the offending line of `yaml` in errors
+    pub fn from_yaml(yaml: &str) -> Result<Self, Box<dyn Error>> {
+        serde_yaml::from_str(yaml).map_err(|err| {
+            let mut message = format!("Invalid config: {}", err);
+            if let Some(location) = err.location() {
+                if let Some(line) = yaml.lines().nth(location.line().saturating_sub(1)) {
+                    message += &format!("\n{:>4} | {}", location.line(), line);
+                }
+            }
+            message.into()
+        })
+    }
+}
+
+// parses a daily block in the form `%R / span`
+fn parse_daily(value: &str) -> Result<(Time, Span), Box<dyn Error>> {
+    let (time, span) = value
+        .split_once('/')
+        .ok_or_else(|| format!("Expected the form '%R / span', got: {}", value))?;
+    Ok((Time::strptime("%R", time.trim())?, parse_span(span)?))
+}
+
+// prefixes the error with the config key it comes from
+fn in_key<T, E: Display>(key: &str, result: Result<T, E>) -> Result<T, Box<dyn Error>> {
+    result.map_err(|err| format!("Invalid config: {}: {}", key, err).into())
 }
 
 impl TryFrom<SchedulerConfig> for Scheduler {
     type Error = Box<dyn Error>;
 
     fn try_from(value: SchedulerConfig) -> Result<Self, Self::Error> {
-        let interval = Interval::new(
-            DateTime::strptime("%F %R", value.start)?
-                .to_zoned(TimeZone::system())?
-                .timestamp(),
-            DateTime::strptime("%F %R", value.end)?
-                .to_zoned(TimeZone::system())?
-                .timestamp(),
+        value.into_scheduler(&SystemClock)
+    }
+}
+
+impl SchedulerConfig {
+    // splits the tasks between `resources`, scheduling each resource on its own. a task chain
+    // stays with one resource, the least loaded one having every skill the chain requires.
+    // without any resources the whole config is one unnamed resource
+    pub fn into_team(
+        mut self,
+        clock: &dyn Clock,
+    ) -> Result<Vec<(String, Scheduler)>, Box<dyn Error>> {
+        if self.resources.is_empty() {
+            return Ok(vec![(String::new(), self.into_scheduler(clock)?)]);
+        }
+        let resources = mem::take(&mut self.resources);
+        let chains = mem::take(&mut self.tasks);
+
+        let tasks: Vec<Task> = in_key("tasks", Tasks::try_from(chains.clone()))?.into();
+        let mut tasks = tasks.into_iter();
+        let mut assigned = vec![Vec::new(); resources.len()];
+        let mut load = vec![0.0; resources.len()];
+        for chain in chains {
+            let chain_tasks: Vec<_> = tasks.by_ref().take(chain.len()).collect();
+            let capable = |resource: &Resource| {
+                chain_tasks
+                    .iter()
+                    .flat_map(|task| &task.requires)
+                    .all(|skill| resource.skills.contains(skill))
+            };
+            let resource_idx = (0..resources.len())
+                .filter(|&idx| capable(&resources[idx]))
+                .min_by(|a, b| total_cmp_f32(load[*a], load[*b]))
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid config: resources: Nobody has the skills needed by {}",
+                        chain_tasks[0].description
+                    )
+                })?;
+            load[resource_idx] += chain_tasks.iter().map(|task| task.volume).sum::<f32>();
+            assigned[resource_idx].push(chain);
+        }
+
+        resources
+            .into_iter()
+            .zip(assigned)
+            .map(|(resource, chains)| {
+                let mut config = self.clone();
+                config.tasks = chains;
+                for (days, day_plans) in resource.plans {
+                    config.plans.entry(days).or_default().extend(day_plans);
+                }
+                let scheduler = config
+                    .into_scheduler(clock)
+                    .map_err(|err| format!("{}: {}", resource.name, err))?;
+                Ok((resource.name, scheduler))
+            })
+            .collect()
+    }
+
+    // like `Scheduler::try_from`, with `now` taken from `clock`
+    pub fn into_scheduler(self, clock: &dyn Clock) -> Result<Scheduler, Box<dyn Error>> {
+        let start = self.round_start.round(
+            in_key("start", parse_timestamp_with_clock(&self.start, clock))?,
+            &TimeZone::system(),
         );
+        let end = in_key("end", parse_timestamp_with_clock(&self.end, clock))?;
+        let interval = Interval::try_new(start, end)
+            .map_err(|_| "Invalid config: end: Expected the end to be after the start")?;
+
+        let mut aliases = default_time_aliases();
+        aliases.extend(self.time_aliases);
+
+        let mut plans = in_key(
+            "plans",
+            Plans::try_from((&interval, &aliases, self.coordinates.as_ref(), self.plans)),
+        )?;
+        if let Some(lunch) = self.lunch {
+            let (time, span) = in_key("lunch", parse_daily(&lunch))?;
+            plans.insert_daily(&interval, time, span, "Lunch", &TimeZone::system());
+        }
+        for (description, entry) in &self.weekly_template {
+            let (weekdays, time, span) = in_key("weekly_template", parse_weekly(entry))?;
+            for occurrence in interval.daily(time, span, &TimeZone::system()) {
+                let weekday = occurrence.start.to_zoned(TimeZone::system()).weekday();
+                if weekdays.contains(&weekday) {
+                    plans.insert_with_overriding(occurrence, description.clone());
+                }
+            }
+        }
+
+        let mut granularities = in_key(
+            "granularity",
+            self.granularity
+                .split(',')
+                .map(parse_span)
+                .collect::<Result<Vec<_>, _>>(),
+        )?
+        .into_iter();
 
         let allocator = TaskAllocatorWithPlans {
-            granularity: value.granularity.parse::<Span>()?,
-            plans: Plans::try_from((&interval, value.plans))?.into(),
+            granularity: in_key(
+                "granularity",
+                granularities
+                    .next()
+                    .ok_or("Expected at least one granularity"),
+            )?,
+            granularities: granularities.collect(),
+            plans,
+            min_notice: in_key(
+                "min_notice",
+                self.min_notice
+                    .map(|min_notice| parse_span(&min_notice))
+                    .transpose(),
+            )?
+            .unwrap_or_default(),
+            min_session: in_key(
+                "min_session",
+                self.min_session
+                    .map(|min_session| parse_span(&min_session))
+                    .transpose(),
+            )?
+            .unwrap_or_default(),
+            soft_plans: self.soft_plans,
+            working_hours: in_key(
+                "working_hours",
+                self.working_hours
+                    .map(|working_hours| parse_time_window(&working_hours))
+                    .transpose(),
+            )?,
+            break_span: in_key(
+                "break",
+                self.break_span
+                    .map(|break_span| parse_span(&break_span))
+                    .transpose(),
+            )?
+            .unwrap_or_default(),
+            daily_capacity: DailyCapacity::default(),
         };
 
-        Ok(Self::new(
-            allocator,
-            Tasks::try_from(value.tasks)?.into(),
-            interval,
-        ))
+        let contexts = in_key(
+            "contexts",
+            Plans::try_from((
+                &interval,
+                &aliases,
+                self.coordinates.as_ref(),
+                self.contexts,
+            )),
+        )?
+        .into();
+        let mut tasks = in_key("tasks", Tasks::try_from(self.tasks))?;
+        in_key("tasks", tasks.expand_recurring(&interval))?;
+        tasks.resolve_daily_volumes(&interval);
+        let milestones = self
+            .milestones
+            .iter()
+            .map(|(name, time)| Ok((name.clone(), parse_timestamp_with_clock(time, clock)?)))
+            .collect::<Result<_, Box<dyn Error>>>();
+        in_key(
+            "tasks",
+            tasks.resolve_milestones(&in_key("milestones", milestones)?),
+        )?;
+        let tasks = tasks.into();
+        let mut scheduler = Scheduler::new(allocator, tasks, interval);
+        scheduler.contexts = contexts;
+        scheduler.max_tasks_per_day = self.max_tasks_per_day;
+        if let Some(priority_cap) = self.priority_cap {
+            if !(priority_cap.is_finite() && priority_cap > 0.0) {
+                return Err(format!(
+                    "Invalid priority_cap: {}, expected a positive number",
+                    priority_cap
+                )
+                .into());
+            }
+            scheduler.priority_cap = Some(priority_cap);
+        }
+        if let Some(drop_below) = self.drop_below {
+            scheduler.drop_below = in_key("drop_below", parse_span(&drop_below))?;
+        }
+        if let Some(frozen_until) = self.frozen_until {
+            scheduler.frozen_until = Some(in_key(
+                "frozen_until",
+                parse_timestamp_with_clock(&frozen_until, clock),
+            )?);
+        }
+        if let Some(deadline_warning) = self.deadline_warning {
+            scheduler.deadline_warning = in_key("deadline_warning", parse_span(&deadline_warning))?;
+        }
+        if let Some(commit_horizon) = self.commit_horizon {
+            scheduler.commit_horizon = Some(in_key("commit_horizon", parse_span(&commit_horizon))?);
+        }
+        if let Some(week_start) = self.week_start {
+            scheduler.week_start = in_key("week_start", parse_weekday(&week_start))?;
+        }
+
+        for (pin, description) in self.pins {
+            let task_idx = in_key(
+                "pins",
+                scheduler
+                    .tasks
+                    .iter()
+                    .position(|task| task.description == description)
+                    .ok_or_else(|| format!("Pinned task not found: {}", description)),
+            )?;
+            let pin_interval = in_key("pins", parse_pin_interval(&pin))?;
+            in_key(
+                "pins",
+                scheduler.pin_task(task_idx, pin_interval, self.pin_conflict),
+            )?;
+        }
+
+        if let Some(review) = self.review {
+            let (time, span) = in_key("review", parse_daily(&review))?;
+            in_key(
+                "review",
+                scheduler.pin_daily(time, span, "Review", self.pin_conflict),
+            )?;
+        }
+
+        if self.strict {
+            if let Some(problem) = scheduler.validate().into_iter().next() {
+                return Err(format!("Invalid config: {}", problem).into());
+            }
+        }
+
+        Ok(scheduler)
+    }
+}
+
+// a single scheduling decision made by `Scheduler::next`
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TraceEntry {
+    pub current_time: Timestamp,
+    pub task_idx: TaskIdx,
+    pub scores: Vec<f32>,
+    pub interval: Interval,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+// a problem found in the tasks, in a stable shape for tools to consume
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+    // e.g. `deadline_before_start`, `dependency_cycle` or `oversubscribed`
+    pub code: &'static str,
+    pub severity: Severity,
+    pub task_idx: Option<TaskIdx>,
+    pub task: Option<String>,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
     }
 }
 
-#[derive(Deref, DerefMut, Deserialize)]
+// a change to one task that would leave enough free time for the tasks due with it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    pub task_idx: TaskIdx,
+    pub change: SuggestedChange,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SuggestedChange {
+    // moving the deadline back by this much
+    ExtendDeadline(SignedDuration),
+    // lowering the priority, or dropping the task, so the others due by then get its time
+    LowerPriority,
+}
+
+impl Display for Suggestion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+#[derive(Deref, Deserialize)]
 #[serde(try_from = "SchedulerConfig")]
 pub struct Scheduler {
     #[deref]
-    #[deref_mut]
     inner: Vec<Vec<Interval>>,
     pub tasks: Vec<Task>,
     pub allocator: TaskAllocatorWithPlans,
     pub interval: Interval,
     pub current_time: Timestamp,
-    pub heuristics: Vec<Heuristic>,
+    // heuristics with the names they are reported by and their `heuristics::placement_phrase`
+    pub heuristics: Vec<(String, Heuristic, Option<&'static str>)>,
+    // nudges added to the combined score of tasks the heuristics did not rule out
+    pub bonus_heuristics: Vec<Heuristic>,
+    // picks the task to schedule next from the combined scores, `best_task` by default
+    pub select: Selector,
+    pub week_start: Weekday,
+    // days, working hours and time windows are taken in this time zone, the system one by default
+    pub tz: TimeZone,
+    // contexts, e.g. `home` or `office`, available on each interval
+    pub contexts: BTreeMap<Interval, String>,
+    // intervals of pinned tasks by the task they belong to, which are never moved
+    pub pinned: Vec<(TaskIdx, Interval)>,
+    // recorded decisions, `None` unless tracing is enabled
+    pub trace: Option<Vec<TraceEntry>>,
+    // zero disables dropping
+    pub drop_below: Span,
+    // cap on the number of different tasks worked on in a day
+    pub max_tasks_per_day: Option<usize>,
+    // the priority heuristic clamps priorities to this
+    pub priority_cap: Option<f32>,
+    // tasks that will not be scheduled any further, with the reason why
+    pub dropped: BTreeMap<TaskIdx, String>,
+    // daily pins cut short by the ends of the scheduling interval or by plans, as they were asked
+    // for
+    pub clipped: Vec<(TaskIdx, Interval)>,
+    // tasks finishing within this span of their deadline are `DeadlineRisk::Amber`, zero disables
+    pub deadline_warning: Span,
+    // nothing is allocated before this and intervals starting before it are never moved
+    pub frozen_until: Option<Timestamp>,
+    // nothing is allocated later than this after the start of the scheduling interval
+    pub commit_horizon: Option<Span>,
+    // why the block picked by `next` at each start was placed there, see `placement_reason`.
+    // `None` unless enabled with `with_reasons`
+    pub reasons: Option<BTreeMap<(Timestamp, TaskIdx), String>>,
+    // scheduled intervals of all tasks by start and task, kept in sync with `inner` by the methods
+    // changing it
+    timeline: BTreeMap<(Timestamp, TaskIdx), Interval>,
 }
 
 pub type Schedule = BTreeMap<String, BTreeMap<String, String>>;
 
+// returning `None` ends the scheduling
+pub type Selector = Box<dyn Fn(&[f32], &Scheduler) -> Option<TaskIdx>>;
+
+// a task as the scheduler sees it once progress, cadences and daily volumes are resolved
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ResolvedTask {
+    pub description: String,
+    // local `%F %R`
+    pub deadline: String,
+    pub priority: f32,
+    // hours left after progress and the time already scheduled
+    pub remaining_volume: f32,
+    pub dependencies: Vec<String>,
+}
+
+// how close a task comes to missing its deadline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadlineRisk {
+    // finishes within `Scheduler::deadline_warning` of the deadline
+    Amber,
+    // misses the deadline
+    Red,
+}
+
+impl DeadlineRisk {
+    // appended to the task in text output
+    pub fn marker(&self) -> &'static str {
+        match self {
+            DeadlineRisk::Amber => "[amber]",
+            DeadlineRisk::Red => "[red]",
+        }
+    }
+}
+
+// how the end of an interval at local midnight is shown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidnightStyle {
+    // `00:00` of the next day
+    Keep,
+    // `24:00` of the day the interval ends on
+    As24,
+}
+
+impl MidnightStyle {
+    // the date and `%R` time to show for `end`
+    pub fn display_end(self, end: &Zoned) -> (Date, String) {
+        if self == MidnightStyle::As24 && end.time() == Time::midnight() {
+            if let Ok(day_before) = end.date().yesterday() {
+                return (day_before, "24:00".to_string());
+            }
+        }
+        (end.date(), end.strftime("%R").to_string())
+    }
+}
+
+// what an instant of the scheduling interval is taken by
+#[derive(Clone, Debug, PartialEq)]
+pub enum Occupancy {
+    Task(TaskIdx),
+    Plan(String),
+    Free,
+}
+
 impl From<&Scheduler> for Schedule {
     fn from(scheduler: &Scheduler) -> Self {
+        scheduler.get_schedule_since(Timestamp::MIN)
+    }
+}
+
+impl Scheduler {
+    // scheduled tasks and plans by description, clipped to start no earlier than `since`
+    pub fn get_intervals_since(&self, since: Timestamp) -> Vec<(String, Interval)> {
         let mut all_intervals = Vec::new();
-        for (task_idx, intervals) in scheduler.inner.iter().enumerate() {
-            for interval in intervals {
-                all_intervals.push((scheduler.tasks[task_idx].description.clone(), interval));
-            }
+        for (task_idx, interval) in self.timeline() {
+            all_intervals.push((self.tasks[task_idx].description.clone(), interval.clone()));
         }
 
-        for (interval, description) in &scheduler.allocator.plans {
-            all_intervals.push((description.clone(), interval));
+        for (interval, description) in self.allocator.plans.iter() {
+            all_intervals.push((description.clone(), interval.clone()));
         }
 
         all_intervals
             .into_iter()
-            .group_by(|(_, interval)| {
-                interval
-                    .start
-                    .to_zoned(TimeZone::system())
-                    .round(ZonedRound::new().smallest(Unit::Day).mode(RoundMode::Trunc))
-                    .expect("Failed to round timestamp")
+            .filter(|(_, interval)| interval.end > since)
+            .map(|(description, interval)| {
+                (
+                    description,
+                    Interval::new(interval.start.max(since), interval.end),
+                )
             })
+            .collect()
+    }
+
+    // only includes intervals ending after `since`, clipping the ones that start before it.
+    // intervals ending at midnight end at `24:00`
+    pub fn get_schedule_since(&self, since: Timestamp) -> Schedule {
+        self.get_schedule_since_with(since, MidnightStyle::As24)
+    }
+
+    pub fn get_schedule_since_with(
+        &self,
+        since: Timestamp,
+        midnight_end: MidnightStyle,
+    ) -> Schedule {
+        self.get_intervals_since(since)
+            .into_iter()
+            .group_by(|(_, interval)| interval.round_start(Unit::Day, RoundMode::Trunc, &self.tz))
             .into_iter()
             .map(|(day, intervals)| {
                 (
-                    day.strftime("%F").to_string(),
+                    day.to_zoned(self.tz.clone()).strftime("%F").to_string(),
                     intervals
                         .into_iter()
                         .map(|(description, interval)| {
-                            (
-                                format!(
-                                    "{} - {}",
-                                    interval.start.to_zoned(TimeZone::system()).strftime("%R"),
-                                    {
-                                        let res = interval
-                                            .end
-                                            .to_zoned(TimeZone::system())
-                                            .strftime("%R")
-                                            .to_string();
-                                        if res == "00:00" {
-                                            "24:00".to_string()
-                                        } else {
-                                            res
-                                        }
-                                    }
-                                ),
-                                description,
-                            )
+                            let (start, end) = interval.as_local_pair(&self.tz);
+                            let (_, end) = midnight_end.display_end(&end);
+                            (format!("{} - {}", start.strftime("%R"), end), description)
                         })
                         .collect(),
                 )
@@ -117,8 +697,29 @@ impl From<&Scheduler> for Schedule {
     }
 }
 
+// orders scores like `f32::total_cmp`, except that NaN is below every other score and zeros of
+// either sign are equal
+pub fn total_cmp_f32(a: f32, b: f32) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+    }
+}
+
+// index of the highest score, the first one on ties
+pub fn best_task(scores: &[f32]) -> Option<TaskIdx> {
+    scores
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| total_cmp_f32(**a, **b).reverse())
+        .map(|(idx, _)| idx)
+}
+
 impl Scheduler {
     pub fn new(allocator: TaskAllocatorWithPlans, tasks: Vec<Task>, interval: Interval) -> Self {
+        let tz = TimeZone::system();
         Self {
             inner: vec![Vec::new(); tasks.len()],
             tasks,
@@ -126,117 +727,1626 @@ impl Scheduler {
             current_time: interval.start,
             interval,
             heuristics: Vec::new(),
+            bonus_heuristics: Vec::new(),
+            select: Box::new(|scores, _| best_task(scores)),
+            week_start: Weekday::Monday,
+            tz,
+            contexts: BTreeMap::new(),
+            pinned: Vec::new(),
+            trace: None,
+            drop_below: Span::new(),
+            max_tasks_per_day: None,
+            priority_cap: None,
+            deadline_warning: Span::new(),
+            frozen_until: None,
+            commit_horizon: None,
+            dropped: BTreeMap::new(),
+            clipped: Vec::new(),
+            reasons: None,
+            timeline: BTreeMap::new(),
         }
     }
 
-    pub fn schedule(&mut self) {
-        while let Some((task_idx, task_interval)) = self.next() {
-            self.schedule_task(task_idx, task_interval);
-        }
-    }
+    // works by iterating over the tasks and applying heuristics to them. the task with the highest
+    // heuristic score will be selected for scheduling. the heuristic scores are multiplied
+    // together. allocator will allocate the interval for the task to be scheduled on.
+    pub fn next_block(&mut self) -> Option<(TaskIdx, Interval)> {
+        loop {
+            let commit_end = self.commit_end();
+            if self.current_time >= commit_end {
+                return None;
+            }
 
-    pub fn schedule_task(&mut self, task_idx: TaskIdx, interval: Interval) {
-        let Some(last_task) = self.iter().position(|intervals| {
-            let Some(last_interval) = intervals.iter().max_by_key(|i| i.end) else {
-                return false;
-            };
-            last_interval.end == interval.start
-        }) else {
-            self[task_idx].push(interval);
-            return;
-        };
+            if self.drop_below.is_positive() {
+                self.drop_short_tasks();
+            }
 
-        if last_task != task_idx {
-            self[task_idx].push(interval);
-            return;
-        }
+            let heuristic_scores = self.combined_scores();
+            if heuristic_scores.iter().sum::<f32>() == 0.0 {
+                // tasks held back by a quota, a milestone, their allowed window or their context
+                // can continue later
+                self.current_time = self.next_quota_reset()?;
+                continue;
+            }
 
-        let last_interval = self[task_idx]
-            .iter_mut()
-            .max_by_key(|interval| interval.end)
-            .expect("Failed to find last interval");
+            let best = (self.select)(&heuristic_scores, self)?;
 
-        last_interval.end = interval.end;
-    }
+            // when the selected task cannot start right away, e.g. because of its allowed window,
+            // the best one that can is scheduled instead
+            let mut candidates: Vec<_> = (0..heuristic_scores.len())
+                .filter(|&idx| idx != best && heuristic_scores[idx] > 0.0)
+                .collect();
+            candidates.sort_by(|a, b| total_cmp_f32(heuristic_scores[*b], heuristic_scores[*a]));
+            candidates.insert(0, best);
+            let (idx, interval) = candidates
+                .into_iter()
+                .map(|idx| (idx, self.allocator.allocate(self, idx)))
+                .find(|(_, interval)| {
+                    interval.start == self.current_time && interval.end > interval.start
+                })
+                .unwrap_or_else(|| (best, self.allocator.allocate(self, best)));
+            if interval.start >= commit_end {
+                return None;
+            }
+            let interval = Interval::new(interval.start, interval.end.min(commit_end));
+            if self.tasks[idx].window_end == WindowEnd::Drop
+                && self.is_cut_short_by_window(idx, &interval)
+            {
+                self.dropped.insert(
+                    idx,
+                    "its next block would be cut short by the end of the window".to_string(),
+                );
+                continue;
+            }
 
-    // works by iterating over the tasks and applying heuristics to them. the task with the highest
-    // heuristic score will be selected for scheduling. the heuristic scores are multiplied
-    // together. allocator will allocate the interval for the task to be scheduled on.
-    pub fn next(&mut self) -> Option<(TaskIdx, Interval)> {
-        if self.current_time >= self.interval.end {
-            return None;
+            if self.reasons.is_some() {
+                let runner_up = (0..heuristic_scores.len())
+                    .filter(|&other| other != idx && heuristic_scores[other] > 0.0)
+                    .min_by(|a, b| {
+                        total_cmp_f32(heuristic_scores[*a], heuristic_scores[*b]).reverse()
+                    });
+                let reason = self.placement_reason(idx, best, runner_up, &interval);
+                if let Some(reasons) = &mut self.reasons {
+                    reasons.insert((interval.start, idx), reason);
+                }
+            }
+
+            if let Some(trace) = &mut self.trace {
+                trace.push(TraceEntry {
+                    current_time: self.current_time,
+                    task_idx: idx,
+                    scores: heuristic_scores,
+                    interval: interval.clone(),
+                });
+            }
+
+            self.current_time = interval.end;
+
+            return Some((idx, interval));
         }
+    }
 
+    // the heuristic scores of all tasks at `current_time` multiplied together, zero for tasks that
+    // are not to be selected, with the bonus heuristics added on top
+    fn combined_scores(&self) -> Vec<f32> {
         let mut heuristic_scores = vec![1.0; self.tasks.len()];
 
-        for heuristic in &self.heuristics {
+        for (_, heuristic, _) in &self.heuristics {
             for (task_idx, score) in heuristic_scores.iter_mut().enumerate() {
                 *score *= heuristic(self, task_idx);
             }
         }
+        for task_idx in self.dropped.keys() {
+            heuristic_scores[*task_idx] = 0.0;
+        }
+        // finished tasks, including ones without any volume, are never selected. stretch tasks are
+        // filled in after the rest
+        for (task_idx, score) in heuristic_scores.iter_mut().enumerate() {
+            let task = &self.tasks[task_idx];
+            if task.stretch || task.volume - self.get_total_task_hours(task_idx) <= f32::EPSILON {
+                *score = 0.0;
+            }
+        }
+        for bonus in &self.bonus_heuristics {
+            for (task_idx, score) in heuristic_scores.iter_mut().enumerate() {
+                if *score > 0.0 {
+                    *score += bonus(self, task_idx);
+                }
+            }
+        }
+        // a contiguous task started today is not interrupted by other tasks unless it cannot
+        // continue
+        if let Some(contiguous_idx) = self.unfinished_contiguous_task() {
+            if heuristic_scores[contiguous_idx] > 0.0 {
+                for (task_idx, score) in heuristic_scores.iter_mut().enumerate() {
+                    if task_idx != contiguous_idx {
+                        *score = 0.0;
+                    }
+                }
+            }
+        }
 
-        if heuristic_scores.iter().sum::<f32>() == 0.0 {
-            return None;
+        heuristic_scores
+    }
+
+    pub fn schedule(&mut self) {
+        while let Some((task_idx, task_interval)) = self.next_block() {
+            self.schedule_task(task_idx, task_interval);
         }
+        self.fill_stretch();
+    }
 
-        let idx = heuristic_scores
-            .iter()
-            .enumerate()
-            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Less).reverse())
-            .expect("Failed to find task with max heuristic score")
-            .0;
+    // schedules the task and the dependencies it transitively waits on, leaving the other tasks
+    // and stretch tasks alone. `current_time` is restored, so the others can still take the gaps
+    pub fn schedule_only(&mut self, task_idx: TaskIdx) {
+        let current_time = self.current_time;
+        let mut included = vec![false; self.tasks.len()];
+        let mut pending = vec![task_idx];
+        while let Some(idx) = pending.pop() {
+            if idx >= included.len() || included[idx] {
+                continue;
+            }
+            included[idx] = true;
+            pending.extend(self.tasks[idx].dependencies.iter().copied());
+        }
+
+        self.heuristics.push((
+            "schedule_only".to_string(),
+            Box::new(move |_, idx| {
+                if included.get(idx).copied().unwrap_or(false) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }),
+            None,
+        ));
+        while let Some((task_idx, task_interval)) = self.next_block() {
+            self.schedule_task(task_idx, task_interval);
+        }
+        self.heuristics.pop();
+        self.current_time = current_time;
+    }
+
+    pub fn add_task(&mut self, task: Task) -> TaskIdx {
+        self.tasks.push(task);
+        self.inner.push(Vec::new());
+        self.tasks.len() - 1
+    }
 
-        let interval = self.allocator.allocate(self, idx);
+    // splits the idle time left between stretch tasks in proportion to their priority, up to their
+    // caps. higher priority tasks are allocated first, under the same constraints as any other
+    // task. stretch tasks without priority get nothing, see `validate`
+    pub fn fill_stretch(&mut self) {
+        let mut stretch: Vec<_> = (0..self.tasks.len())
+            .filter(|&idx| self.tasks[idx].stretch && self.tasks[idx].priority > 0.0)
+            .collect();
+        if stretch.is_empty() {
+            return;
+        }
+        stretch.sort_by(|a, b| total_cmp_f32(self.tasks[*b].priority, self.tasks[*a].priority));
 
-        self.current_time = interval.end;
+        let committed = Interval::new(self.interval.start, self.commit_end());
+        let idle: Vec<_> = self
+            .idle_intervals()
+            .iter()
+            .filter_map(|idle| idle.clip_to(&committed))
+            .collect();
+        let mut left: f32 = idle.iter().map(Interval::hours).sum();
+        let mut shares = vec![0.0; self.tasks.len()];
+        let mut uncapped = stretch.clone();
+        // tasks whose proportional share exceeds their cap get the cap, the rest is split again
+        loop {
+            let total_priority: f32 = uncapped.iter().map(|&idx| self.tasks[idx].priority).sum();
+            let capped: Vec<_> = uncapped
+                .iter()
+                .copied()
+                .filter(|&idx| {
+                    let task = &self.tasks[idx];
+                    task.stretch_cap
+                        .is_some_and(|cap| left * task.priority / total_priority > cap)
+                })
+                .collect();
+            if capped.is_empty() {
+                for &idx in &uncapped {
+                    shares[idx] = left * self.tasks[idx].priority / total_priority;
+                }
+                break;
+            }
+            for idx in capped {
+                shares[idx] = self.tasks[idx].stretch_cap.unwrap_or_default();
+                left -= shares[idx];
+                uncapped.retain(|&other| other != idx);
+            }
+        }
 
-        Some((idx, interval))
+        // stretch tasks keep their volume, the hours still owed are tracked in `shares` instead
+        let commit_end = self.commit_end();
+        let current_time = mem::replace(&mut self.current_time, self.interval.start);
+        for task_idx in stretch {
+            while shares[task_idx] > 1.0 / 3600.0 {
+                let block = self
+                    .allocator
+                    .allocate_hours(self, task_idx, shares[task_idx]);
+                if block.end <= block.start || block.start >= commit_end {
+                    break;
+                }
+                let block = Interval::new(block.start, block.end.min(commit_end));
+                shares[task_idx] -= block.hours();
+                self.schedule_task(task_idx, block);
+            }
+        }
+        self.current_time = current_time;
     }
 
-    pub fn get_last_task(&self) -> Option<TaskIdx> {
-        self.iter()
-            .enumerate()
-            .max_by_key(|(_, intervals)| intervals.iter().map(|interval| interval.end).max())
-            .and_then(|(task_idx, intervals)| {
-                if intervals.is_empty() {
-                    None
-                } else {
-                    Some(task_idx)
+    // schedules the task on a fixed interval, resolving overlaps with plans according to `policy`
+    pub fn pin_task(
+        &mut self,
+        task_idx: TaskIdx,
+        interval: Interval,
+        policy: PinConflict,
+    ) -> Result<(), Box<dyn Error>> {
+        let task = self
+            .task(task_idx)
+            .ok_or_else(|| format!("Pinned task index out of range: {}", task_idx))?;
+        let description = task.description.clone();
+        // pins are never moved, so overlapping ones would double-book the overlap
+        if let Some((other_idx, overlap)) = self
+            .pinned
+            .iter()
+            .find_map(|(other_idx, pinned)| Some((*other_idx, pinned.clip_to(&interval)?)))
+        {
+            let (start, end) = overlap.as_local_pair(&self.tz);
+            return Err(format!(
+                "Pinned tasks {} and {} overlap on {} - {}",
+                description,
+                self.tasks[other_idx].description,
+                start.strftime("%F %R"),
+                end.strftime("%R")
+            )
+            .into());
+        }
+        let overlapping_plans: Vec<_> = self
+            .allocator
+            .plans
+            .iter()
+            .filter(|(plan, _)| plan.intercepts(&interval))
+            .map(|(plan, description)| (plan.clone(), description.clone()))
+            .collect();
+
+        let pieces = match policy {
+            PinConflict::PinWins => {
+                // the pin overrides plans like a plan would, but is kept as a task only
+                self.allocator
+                    .plans
+                    .insert_with_overriding(interval.clone(), description.clone());
+                self.allocator.plans.remove(&interval);
+                vec![interval]
+            }
+            PinConflict::PlanWins => {
+                interval.difference(overlapping_plans.iter().map(|(plan, _)| plan))
+            }
+            PinConflict::Error => {
+                if let Some((_, plan_description)) = overlapping_plans.first() {
+                    return Err(format!(
+                        "Pinned task {} overlaps plan {}",
+                        description, plan_description
+                    )
+                    .into());
                 }
-            })
+                vec![interval]
+            }
+        };
+        for piece in pieces {
+            self.pinned.push((task_idx, piece.clone()));
+            if let Some(reasons) = &mut self.reasons {
+                reasons.insert((piece.start, task_idx), "pinned here".to_string());
+            }
+            self.schedule_task(task_idx, piece);
+        }
+
+        Ok(())
     }
 
-    pub fn add_heuristic(mut self, heuristic: Heuristic) -> Self {
-        self.heuristics.push(heuristic);
-        self
+    // adds a task pinned at `time` for `span` on each day of the scheduling interval. its volume
+    // is what got pinned, occurrences that did not fit whole are listed in `clipped`
+    pub fn pin_daily(
+        &mut self,
+        time: Time,
+        span: Span,
+        description: &str,
+        policy: PinConflict,
+    ) -> Result<TaskIdx, Box<dyn Error>> {
+        let task_idx = self.add_task(Task {
+            description: description.to_string(),
+            deadline: self.interval.end,
+            priority: 0.0,
+            ..Default::default()
+        });
+
+        for occurrence in self.interval.daily(time, span, &self.tz) {
+            let pinned_hours = self.get_total_task_hours(task_idx);
+            if let Some(piece) = occurrence.clip_to(&self.interval) {
+                self.pin_task(task_idx, piece, policy)?;
+            }
+            if self.get_total_task_hours(task_idx) - pinned_hours
+                < occurrence.hours() - 1.0 / 3600.0
+            {
+                self.clipped.push((task_idx, occurrence));
+            }
+        }
+        self.tasks[task_idx].volume = self.get_total_task_hours(task_idx);
+
+        Ok(task_idx)
     }
 
-    pub fn get_total_task_hours(&self, task_idx: TaskIdx) -> f32 {
-        self.inner
-            .get(task_idx)
-            .map(|intervals| {
+    // extends the task's interval ending right at `interval.start`, if any, or adds a new one
+    pub fn schedule_task(&mut self, task_idx: TaskIdx, interval: Interval) {
+        match self.inner[task_idx]
+            .iter_mut()
+            .find(|previous| previous.end == interval.start)
+        {
+            Some(previous) => {
+                previous.end = interval.end;
+                self.timeline
+                    .insert((previous.start, task_idx), previous.clone());
+            }
+            None => {
+                self.inner[task_idx].push(interval.clone());
+                self.timeline.insert((interval.start, task_idx), interval);
+            }
+        }
+    }
+
+    // rebuilds `timeline` after `inner` was changed wholesale
+    fn rebuild_timeline(&mut self) {
+        self.timeline = self
+            .inner
+            .iter()
+            .enumerate()
+            .flat_map(|(task_idx, intervals)| {
                 intervals
                     .iter()
-                    .map(|interval| interval.hours())
-                    .sum::<f32>()
+                    .map(move |interval| ((interval.start, task_idx), interval.clone()))
             })
-            .unwrap_or(0.0)
+            .collect();
     }
 
-    pub fn get_planned_hours(&self, interval: Interval) -> f32 {
+    // scheduled tasks take precedence over plans, since pins may override them. intervals are
+    // half-open, so an instant where one interval ends and the next starts belongs to the next
+    pub fn occupancy_at(&self, timestamp: Timestamp) -> Occupancy {
+        if let Some(((_, task_idx), _)) = self
+            .timeline
+            .range(..=(timestamp, TaskIdx::MAX))
+            .rev()
+            .find(|(_, interval)| interval.contains_timestamp(timestamp))
+        {
+            return Occupancy::Task(*task_idx);
+        }
+
         self.allocator
             .plans
-            .keys()
-            .filter(|plan| plan.intercepts(&interval))
-            .map(|plan| plan.hours())
-            .sum::<f32>()
+            .iter()
+            .find(|(interval, _)| interval.contains_timestamp(timestamp))
+            .map_or(Occupancy::Free, |(_, description)| {
+                Occupancy::Plan(description.clone())
+            })
     }
 
-    pub fn get_missed_deadlines_tasks(&self) -> Vec<TaskIdx> {
-        self.tasks
+    // all scheduled intervals sorted by start, then by task, for comparing schedules
+    pub fn schedule_snapshot(&self) -> Vec<(TaskIdx, Interval)> {
+        let mut snapshot: Vec<_> = self
+            .iter()
+            .enumerate()
+            .flat_map(|(task_idx, intervals)| intervals.iter().map(move |i| (task_idx, i.clone())))
+            .collect();
+        snapshot.sort_by_key(|(task_idx, interval)| (interval.start, *task_idx));
+
+        snapshot
+    }
+
+    // scheduled intervals of all tasks in chronological order, intervals starting together by task
+    pub fn timeline(&self) -> impl DoubleEndedIterator<Item = (TaskIdx, &Interval)> {
+        self.timeline
+            .iter()
+            .map(|((_, task_idx), interval)| (*task_idx, interval))
+    }
+
+    // shifts scheduled intervals into the earliest idle gap before them that fits them whole,
+    // without starting before the task's dependencies finish or its `not_before`, leaving its
+    // allowed window or starting without its context. pinned intervals stay in place
+    pub fn compact(&mut self) {
+        let mut all_intervals: Vec<_> = self
             .iter()
             .enumerate()
-            .filter(|(idx, task)| task.volume - self.get_total_task_hours(*idx) != 0.0)
+            .flat_map(|(task_idx, intervals)| intervals.iter().map(move |i| (task_idx, i.clone())))
+            .collect();
+        all_intervals.sort_by_key(|(_, interval)| interval.start);
+
+        for (task_idx, interval) in all_intervals {
+            if self.pinned.iter().any(|(_, pin)| pin.intercepts(&interval))
+                || self.is_frozen(&interval)
+            {
+                continue;
+            }
+
+            let task = &self.tasks[task_idx];
+            let dependencies_end = task
+                .dependencies
+                .iter()
+                .filter_map(|&dependency_idx| self.get(dependency_idx))
+                .flat_map(|intervals| intervals.iter().map(|i| i.end))
+                .max()
+                .unwrap_or(self.interval.start);
+            let earliest_start =
+                dependencies_end.max(task.not_before.unwrap_or(self.interval.start));
+            let blocked_by_window = task
+                .allowed_window
+                .as_ref()
+                .map(|window| window.complement(&self.interval, &self.tz))
+                .unwrap_or_default();
+
+            let Some(new_interval) = self
+                .idle_intervals()
+                .into_iter()
+                .take_while(|gap| gap.start < interval.start)
+                .filter_map(|gap| {
+                    let new_interval =
+                        Interval::from_span(gap.start.max(earliest_start), interval.span());
+                    (gap.contains(&new_interval)
+                        && new_interval.start < interval.start
+                        && !blocked_by_window
+                            .iter()
+                            .any(|b| b.intercepts(&new_interval))
+                        && task.context.as_ref().is_none_or(|required_context| {
+                            self.contexts.iter().any(|(available, context)| {
+                                context == required_context
+                                    && available.contains_timestamp(new_interval.start)
+                            })
+                        }))
+                    .then_some(new_interval)
+                })
+                .next()
+            else {
+                continue;
+            };
+
+            // the recorded reasons explain the old placement
+            self.take_reasons(task_idx, &interval);
+            let intervals = &mut self.inner[task_idx];
+            intervals.retain(|i| *i != interval);
+            intervals.push(new_interval);
+            intervals.sort();
+            let mut merged: Vec<Interval> = Vec::new();
+            for interval in intervals.drain(..) {
+                match merged.last_mut() {
+                    Some(last) if last.end == interval.start => last.end = interval.end,
+                    _ => merged.push(interval),
+                }
+            }
+            *intervals = merged;
+
+            self.timeline.retain(|(_, idx), _| *idx != task_idx);
+            for interval in &self.inner[task_idx] {
+                self.timeline
+                    .insert((interval.start, task_idx), interval.clone());
+            }
+        }
+    }
+
+    // the block ends with the window, shorter than the task's remaining work and granularity
+    fn is_cut_short_by_window(&self, task_idx: TaskIdx, interval: &Interval) -> bool {
+        let task = &self.tasks[task_idx];
+        let granularity = task.granularity.unwrap_or(self.allocator.granularity);
+        let full_hours = (task.volume - self.get_total_task_hours(task_idx))
+            .min(Interval::from_span(interval.start, granularity).hours());
+        interval.end >= self.commit_end() && interval.hours() < full_hours - 1.0 / 3600.0
+    }
+
+    // the end of the scheduling interval or of the commit horizon, whichever comes first
+    pub fn commit_end(&self) -> Timestamp {
+        self.commit_horizon
+            .and_then(|commit_horizon| {
+                self.interval
+                    .start
+                    .to_zoned(self.tz.clone())
+                    .checked_add(commit_horizon)
+                    .ok()
+            })
+            .map_or(self.interval.end, |commit_end| {
+                commit_end.timestamp().min(self.interval.end)
+            })
+    }
+
+    // the start of the scheduling interval up to `frozen_until`, if it is later
+    pub fn frozen_zone(&self) -> Option<Interval> {
+        self.frozen_until
+            .filter(|frozen_until| *frozen_until > self.interval.start)
+            .map(|frozen_until| Interval::new(self.interval.start, frozen_until))
+    }
+
+    // intervals starting in the frozen zone stay where they are
+    pub fn is_frozen(&self, interval: &Interval) -> bool {
+        self.frozen_until
+            .is_some_and(|frozen_until| interval.start < frozen_until)
+    }
+
+    // moves every scheduled interval that is neither pinned nor frozen by `span`. fails without
+    // moving anything if an interval would end up in the frozen zone, on a plan, on a block that
+    // stays in place or outside of the scheduling interval
+    pub fn shift_all(&mut self, span: Span) -> Result<(), Box<dyn Error>> {
+        let stays = |interval: &Interval| {
+            self.pinned.iter().any(|(_, pin)| pin.intercepts(interval)) || self.is_frozen(interval)
+        };
+        // frozen blocks may run past `frozen_until`, so moved blocks are checked against all of
+        // them
+        let staying: Vec<_> = self
+            .timeline()
+            .filter(|(_, interval)| stays(interval))
+            .map(|(task_idx, interval)| (task_idx, interval.clone()))
+            .collect();
+        let mut shifted = self.inner.clone();
+        let mut moves = Vec::new();
+        for (task_idx, interval) in shifted
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(task_idx, intervals)| intervals.iter_mut().map(move |i| (task_idx, i)))
+        {
+            if stays(interval) {
+                continue;
+            }
+            let moved = Interval::new(
+                interval.start.checked_add(span)?,
+                interval.end.checked_add(span)?,
+            );
+            if self.is_frozen(&moved) || !self.interval.contains(&moved) {
+                return Err(format!(
+                    "Shifting by {} moves an interval out of the schedulable time",
+                    span
+                )
+                .into());
+            }
+            let description = &self.tasks[task_idx].description;
+            if let Some((_, plan_description)) = self
+                .allocator
+                .plans
+                .iter()
+                .find(|(plan, _)| plan.intercepts(&moved))
+            {
+                return Err(format!(
+                    "Shifting by {} moves task {} onto plan {}",
+                    span, description, plan_description
+                )
+                .into());
+            }
+            if let Some((other_idx, _)) = staying
+                .iter()
+                .find(|(_, staying)| staying.intercepts(&moved))
+            {
+                return Err(format!(
+                    "Shifting by {} moves task {} onto task {}, which stays in place",
+                    span, description, self.tasks[*other_idx].description
+                )
+                .into());
+            }
+            moves.push((task_idx, interval.clone(), moved.start));
+            *interval = moved;
+        }
+
+        self.inner = shifted;
+        self.rebuild_timeline();
+        // all reasons are taken out before any is put back, as moved blocks can take the place of
+        // others
+        let mut moved_reasons = Vec::new();
+        for (task_idx, interval, start) in moves {
+            for (offset, reason) in self.take_reasons(task_idx, &interval) {
+                moved_reasons.push(((start + offset, task_idx), reason));
+            }
+        }
+        if let Some(reasons) = &mut self.reasons {
+            reasons.extend(moved_reasons);
+        }
+        Ok(())
+    }
+
+    // removes the recorded reasons of the task's blocks in `interval`, returning them by how far
+    // into the interval their block starts
+    fn take_reasons(
+        &mut self,
+        task_idx: TaskIdx,
+        interval: &Interval,
+    ) -> Vec<(SignedDuration, String)> {
+        let Some(reasons) = &mut self.reasons else {
+            return Vec::new();
+        };
+        let keys: Vec<_> = reasons
+            .range((interval.start, task_idx)..(interval.end, task_idx))
+            .map(|(key, _)| *key)
+            .filter(|(_, idx)| *idx == task_idx)
+            .collect();
+        keys.into_iter()
+            .filter_map(|key| {
+                let reason = reasons.remove(&key)?;
+                Some((key.0.duration_since(interval.start), reason))
+            })
+            .collect()
+    }
+
+    // free parts of the scheduling interval, not covered by plans, scheduled tasks, min notice or
+    // the frozen zone
+    pub fn idle_intervals(&self) -> Vec<Interval> {
+        let mut blocked: Vec<_> = self.allocator.plans.keys().cloned().collect();
+        blocked.extend(self.timeline().map(|(_, interval)| interval.clone()));
+        blocked.extend(self.frozen_zone());
+        if self.allocator.min_notice.is_positive() {
+            blocked.push(Interval::from_span(
+                self.interval.start,
+                self.allocator.min_notice,
+            ));
+        }
+        blocked.extend(
+            self.allocator
+                .outside_working_hours(&self.interval, &self.tz),
+        );
+
+        self.interval.difference(&blocked)
+    }
+
+    // the task last worked on before `current_time` on the current day, if it is contiguous per day
+    // and still has work left for that day
+    fn unfinished_contiguous_task(&self) -> Option<TaskIdx> {
+        let day = self.get_day(self.current_time);
+        let ((_, task_idx), _) = self
+            .timeline
+            .range((day.start, 0)..(self.current_time, 0))
+            .next_back()?;
+        let task = &self.tasks[*task_idx];
+        let remaining = task.volume - self.get_total_task_hours(*task_idx);
+        let left_today = task.daily_volume.map_or(remaining, |daily_volume| {
+            remaining.min(daily_volume - self.get_task_hours_on(*task_idx, &day))
+        });
+
+        (task.contiguous_per_day && left_today > f32::EPSILON).then_some(*task_idx)
+    }
+
+    // the next time after `current_time` at which a task held back by one of the heuristics below
+    // can continue, if it is within the scheduling interval: the day or week start giving a daily
+    // volume, the day's task cap or a weekly budget a fresh quota, a milestone passing, an allowed
+    // window opening or a context becoming available. `None` if no unfinished task is held back
+    fn next_quota_reset(&self) -> Option<Timestamp> {
+        let unfinished: Vec<_> = (0..self.tasks.len())
+            .filter(|&idx| {
+                let task = &self.tasks[idx];
+                !task.stretch
+                    && !self.dropped.contains_key(&idx)
+                    && task.volume - self.get_total_task_hours(idx) > f32::EPSILON
+            })
+            .collect();
+        let held_by = |heuristic: fn(&Scheduler, TaskIdx) -> f32| {
+            unfinished
+                .iter()
+                .copied()
+                .filter(move |&idx| heuristic(self, idx) == 0.0)
+        };
+
+        let next_day = held_by(heuristics::daily_volume)
+            .chain(held_by(heuristics::max_tasks_per_day))
+            .next()
+            .map(|_| {
+                // days without any free time cannot use a fresh quota either
+                let mut day = self.get_day(self.current_time);
+                while day.end < self.interval.end
+                    && !self.allocator.has_capacity_on(self, &self.get_day(day.end))
+                {
+                    day = self.get_day(day.end);
+                }
+                day.end
+            });
+        let next_week = held_by(heuristics::weekly_budget)
+            .next()
+            .map(|_| self.get_week(self.current_time).end);
+        let next_milestone = held_by(heuristics::milestone)
+            .filter_map(|idx| self.tasks[idx].not_before)
+            .min();
+        let next_window = held_by(heuristics::time_window)
+            .filter_map(|idx| self.tasks[idx].allowed_window.as_ref())
+            .map(|window| window.next_opening(self.current_time, &self.tz))
+            .min();
+        let next_context = held_by(heuristics::context)
+            .filter_map(|idx| self.tasks[idx].context.as_ref())
+            .filter_map(|required_context| {
+                self.contexts
+                    .iter()
+                    .find(|(interval, context)| {
+                        *context == required_context && interval.start > self.current_time
+                    })
+                    .map(|(interval, _)| interval.start)
+            })
+            .min();
+
+        next_day
+            .into_iter()
+            .chain(next_week)
+            .chain(next_milestone)
+            .chain(next_window)
+            .chain(next_context)
+            .filter(|reset| *reset > self.current_time)
+            .min()
+            .filter(|reset| *reset < self.interval.end)
+    }
+
+    // idle intervals between `current_time` and the task's deadline, inside its allowed window
+    pub fn gaps_before_deadline(&self, task_idx: TaskIdx) -> Vec<Interval> {
+        let Some(task) = self.task(task_idx) else {
+            return Vec::new();
+        };
+        if task.deadline <= self.current_time {
+            return Vec::new();
+        }
+
+        let before_deadline = Interval::new(self.current_time, task.deadline);
+        let outside_window = task
+            .allowed_window
+            .as_ref()
+            .map(|window| window.complement(&before_deadline, &self.tz))
+            .unwrap_or_default();
+        self.idle_intervals()
+            .iter()
+            .filter(|idle| idle.intercepts(&before_deadline))
+            .map(|idle| {
+                Interval::new(
+                    idle.start.max(before_deadline.start),
+                    idle.end.min(before_deadline.end),
+                )
+            })
+            .flat_map(|gap| gap.difference(&outside_window))
+            .collect()
+    }
+
+    // hours of idle time within `interval`
+    pub fn capacity_between(&self, interval: &Interval) -> f32 {
+        self.idle_intervals()
+            .iter()
+            .filter(|idle| idle.intercepts(interval))
+            .map(|idle| {
+                Interval::new(idle.start.max(interval.start), idle.end.min(interval.end)).hours()
+            })
+            .sum()
+    }
+
+    // drops unfinished tasks with less than `drop_below` of capacity left before their deadline
+    fn drop_short_tasks(&mut self) {
+        let threshold = Interval::from_span(self.current_time, self.drop_below).hours();
+        for task_idx in 0..self.tasks.len() {
+            let task = &self.tasks[task_idx];
+            if self.dropped.contains_key(&task_idx)
+                || task.volume - self.get_total_task_hours(task_idx) <= f32::EPSILON
+                || self.is_exclusive_sibling_complete(task_idx)
+            {
+                continue;
+            }
+
+            let before_deadline =
+                Interval::new(self.current_time, task.deadline.max(self.current_time));
+            let capacity = self.capacity_between(&before_deadline);
+            if capacity < threshold {
+                self.dropped.insert(
+                    task_idx,
+                    format!("only {:.2} hour(s) available before the deadline", capacity),
+                );
+            }
+        }
+    }
+
+    // average over the scheduled tasks of the number of blocks of work on the task, divided by the
+    // fewest blocks its hours fit in. touching intervals form one run, which counts as the number
+    // of granularity long blocks it takes, so work split only at granularity boundaries scores the
+    // same 1.0 as work done in one go. higher is more scattered
+    pub fn fragmentation_score(&self) -> f32 {
+        let ratios: Vec<_> = self
+            .iter()
+            .enumerate()
+            .filter(|(_, intervals)| !intervals.is_empty())
+            .map(|(task_idx, intervals)| {
+                let mut intervals = intervals.clone();
+                intervals.sort();
+                let granularity = self.tasks[task_idx]
+                    .granularity
+                    .unwrap_or(self.allocator.granularity);
+                let granularity_hours =
+                    Interval::from_span(intervals[0].start, granularity).hours();
+                let blocks_in = |hours: f32| (hours / granularity_hours - 1e-3).ceil().max(1.0);
+
+                let mut runs: Vec<(Timestamp, f32)> = Vec::new();
+                for interval in &intervals {
+                    match runs.last_mut() {
+                        Some((end, hours)) if interval.start <= *end => {
+                            *end = interval.end.max(*end);
+                            *hours += interval.hours();
+                        }
+                        _ => runs.push((interval.end, interval.hours())),
+                    }
+                }
+                let blocks: f32 = runs.iter().map(|(_, hours)| blocks_in(*hours)).sum();
+                blocks / blocks_in(runs.iter().map(|(_, hours)| hours).sum())
+            })
+            .collect();
+        if ratios.is_empty() {
+            return 1.0;
+        }
+
+        ratios.iter().sum::<f32>() / ratios.len() as f32
+    }
+
+    // earliest time the task's remaining work could be finished if it were placed as early as
+    // possible after `current_time` and its scheduled dependencies, only yielding to plans, pins
+    // and work on tasks of at least its priority. `None` if it does not fit in the interval
+    pub fn first_feasible_deadline(&self, task_idx: TaskIdx) -> Option<Timestamp> {
+        let task = self.task(task_idx)?;
+        let mut remaining = task.volume - self.get_total_task_hours(task_idx);
+        if remaining <= f32::EPSILON {
+            return self.earliest_finish(task_idx);
+        }
+
+        let earliest_start = task
+            .dependencies
+            .iter()
+            .filter_map(|&dependency_idx| self.get(dependency_idx))
+            .flatten()
+            .map(|interval| interval.end)
+            .chain([self.current_time])
+            .chain(task.not_before)
+            .max()
+            .expect("Failed to find earliest start");
+        if earliest_start >= self.interval.end {
+            return None;
+        }
+
+        let mut blocked: Vec<_> = self.allocator.plans.keys().cloned().collect();
+        blocked.extend(self.pinned.iter().map(|(_, pin)| pin.clone()));
+        blocked.extend(self.frozen_zone());
+        blocked.extend(
+            self.timeline()
+                .filter(|(idx, _)| *idx != task_idx && self.tasks[*idx].priority >= task.priority)
+                .map(|(_, interval)| interval.clone()),
+        );
+        if self.allocator.min_notice.is_positive() {
+            blocked.push(Interval::from_span(
+                self.interval.start,
+                self.allocator.min_notice,
+            ));
+        }
+        let window = Interval::new(earliest_start, self.interval.end);
+        blocked.extend(self.allocator.outside_working_hours(&window, &self.tz));
+        if let Some(allowed_window) = &task.allowed_window {
+            blocked.extend(allowed_window.complement(&window, &self.tz));
+        }
+
+        for gap in window.difference(&blocked) {
+            let hours = gap.hours();
+            if hours >= remaining {
+                return Some(gap.start + ((remaining * 3600.0).round() as i64).seconds());
+            }
+            remaining -= hours;
+        }
+
+        None
+    }
+
+    // span from the start of the scheduling interval to the end of the last scheduled interval
+    pub fn makespan(&self) -> Option<Span> {
+        let last_end = self.timeline().map(|(_, interval)| interval.end).max()?;
+        Some(last_end - self.interval.start)
+    }
+
+    // end of the task's last scheduled interval, if the task is scheduled to completion
+    pub fn earliest_finish(&self, task_idx: TaskIdx) -> Option<Timestamp> {
+        let task = self.task(task_idx)?;
+        if task.volume - self.get_total_task_hours(task_idx) > f32::EPSILON {
+            return None;
+        }
+        self[task_idx].iter().map(|interval| interval.end).max()
+    }
+
+    // hours of work left across the tasks, leaving out stretch tasks, which have no fixed volume
+    pub fn remaining_volume_total(&self) -> f32 {
+        (0..self.tasks.len())
+            .filter(|&idx| !self.tasks[idx].stretch)
+            .map(|idx| (self.tasks[idx].volume - self.get_total_task_hours(idx)).max(0.0))
+            .sum()
+    }
+
+    // when the remaining work would be done if it took up the idle time from `current_time` on,
+    // regardless of deadlines and windows, but no earlier than the last scheduled interval ends.
+    // `None` if the idle time left in the scheduling interval is not enough
+    pub fn estimated_completion(&self) -> Option<Timestamp> {
+        let last_end = self.timeline().map(|(_, interval)| interval.end).max();
+        let mut remaining = self.remaining_volume_total();
+        let mut completion = self.current_time;
+
+        if remaining > f32::EPSILON {
+            let after_now = Interval::new(self.current_time, self.interval.end);
+            let idle = self
+                .idle_intervals()
+                .into_iter()
+                .filter_map(|idle| idle.clip_to(&after_now));
+            let mut finished = None;
+            for idle in idle {
+                let hours = idle.hours();
+                if hours >= remaining {
+                    let seconds = (remaining * 3600.0).round() as i64;
+                    finished = Some(idle.start + seconds.seconds());
+                    break;
+                }
+                remaining -= hours;
+            }
+            completion = finished?;
+        }
+
+        Some(last_end.map_or(completion, |last_end| last_end.max(completion)))
+    }
+
+    // `Red` for missed deadlines, `Amber` for tasks finishing within `deadline_warning` of theirs
+    pub fn deadline_risk(&self, task_idx: TaskIdx) -> Option<DeadlineRisk> {
+        let task = self.task(task_idx)?;
+        if self.get_missed_deadlines_tasks().contains(&task_idx) {
+            return Some(DeadlineRisk::Red);
+        }
+        let finish = self.earliest_finish(task_idx)?;
+        if finish > task.deadline {
+            return Some(DeadlineRisk::Red);
+        }
+        if !self.deadline_warning.is_positive() {
+            return None;
+        }
+
+        let warning_start = task
+            .deadline
+            .to_zoned(self.tz.clone())
+            .checked_sub(self.deadline_warning)
+            .ok()?
+            .timestamp();
+        (finish > warning_start).then_some(DeadlineRisk::Amber)
+    }
+
+    pub fn task(&self, task_idx: TaskIdx) -> Option<&Task> {
+        self.tasks.get(task_idx)
+    }
+
+    // the task of the interval starting last, which is not always the one ending last when
+    // intervals overlap
+    pub fn get_last_task(&self) -> Option<TaskIdx> {
+        self.timeline().next_back().map(|(task_idx, _)| task_idx)
+    }
+
+    pub fn with_time_zone(mut self, tz: TimeZone) -> Self {
+        self.tz = tz;
+        self
+    }
+
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Vec::new());
+        self
+    }
+
+    pub fn with_reasons(mut self) -> Self {
+        self.reasons = Some(BTreeMap::new());
+        self
+    }
+
+    // the recorded reason for the task's scheduled interval starting at `start`
+    pub fn reason(&self, task_idx: TaskIdx, start: Timestamp) -> Option<&str> {
+        self.reasons
+            .as_ref()?
+            .get(&(start, task_idx))
+            .map(String::as_str)
+    }
+
+    // returns the decisions recorded since the last call, leaving tracing enabled
+    pub fn take_trace(&mut self) -> Vec<TraceEntry> {
+        self.trace.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    // the heuristic is named after its position, use `add_named_heuristic` to give it a name
+    pub fn add_heuristic(self, heuristic: impl Fn(&Scheduler, TaskIdx) -> f32 + 'static) -> Self {
+        let name = format!("heuristic {}", self.heuristics.len());
+        self.add_named_heuristic(name, heuristic)
+    }
+
+    // e.g. `add_named_heuristic("just_in_time", heuristics::just_in_time(0.05))`
+    pub fn add_named_heuristic(
+        mut self,
+        name: impl Into<String>,
+        heuristic: impl Fn(&Scheduler, TaskIdx) -> f32 + 'static,
+    ) -> Self {
+        let phrase = heuristics::placement_phrase(&heuristic);
+        self.heuristics
+            .push((name.into(), Box::new(heuristic), phrase));
+        self
+    }
+
+    // why `next_block` picks the task for `interval`: the better scored `best` task could not
+    // start yet, it continues a contiguous task, or what favours it the most over `runner_up`, the
+    // best scored task it beat. followed by the allocator constraint that shaped the block, if any
+    fn placement_reason(
+        &self,
+        task_idx: TaskIdx,
+        best: TaskIdx,
+        runner_up: Option<TaskIdx>,
+        interval: &Interval,
+    ) -> String {
+        let score = |idx| {
+            self.heuristics
+                .iter()
+                .map(|(_, heuristic, _)| heuristic(self, idx))
+                .product::<f32>()
+        };
+        let bonus = |idx| {
+            self.bonus_heuristics
+                .iter()
+                .map(|bonus| bonus(self, idx))
+                .sum::<f32>()
+        };
+        let mut reason = match runner_up {
+            _ if task_idx != best => "the best scored task could not start yet".to_string(),
+            _ if self.unfinished_contiguous_task() == Some(task_idx) => {
+                "continues the contiguous task started today".to_string()
+            }
+            None => "the only task that could run".to_string(),
+            Some(runner_up)
+                if score(task_idx) <= score(runner_up) && bonus(task_idx) > bonus(runner_up) =>
+            {
+                "nudged ahead by a bonus heuristic".to_string()
+            }
+            Some(runner_up) => {
+                let favouring = self
+                    .heuristics
+                    .iter()
+                    .map(|(name, heuristic, phrase)| {
+                        let lead = (heuristic(self, task_idx) / heuristic(self, runner_up)).ln();
+                        (name, phrase, lead)
+                    })
+                    .filter(|(_, _, lead)| *lead > 0.0)
+                    .max_by(|(_, _, a), (_, _, b)| total_cmp_f32(*a, *b));
+                match favouring {
+                    None => "scored as high as the other tasks".to_string(),
+                    Some((_, Some(phrase), _)) => phrase.to_string(),
+                    Some((name, None, _)) => format!("favoured by {}", name),
+                }
+            }
+        };
+
+        let remaining = self.tasks[task_idx].volume - self.get_total_task_hours(task_idx);
+        let plan_after = self
+            .allocator
+            .plans
+            .range(Interval::new(interval.end, interval.end)..)
+            .next()
+            .filter(|(plan, _)| plan.start == interval.end);
+        if plan_after.is_some() && remaining - interval.hours() > f32::EPSILON {
+            reason.push_str(", only free slot before a plan");
+        } else if interval.start > self.current_time {
+            reason.push_str(", first free slot");
+        }
+        reason
+    }
+
+    // the heuristic that moved the task's score furthest from neutral for the next selection,
+    // either up or down. as scores are multiplied, a heuristic zeroing the task always wins.
+    // `None` if no heuristic changed the score
+    pub fn dominant_heuristic(&self, task_idx: TaskIdx) -> Option<String> {
+        if task_idx >= self.tasks.len() {
+            return None;
+        }
+        self.heuristics
+            .iter()
+            .map(|(name, heuristic, _)| (name, heuristic(self, task_idx).ln().abs()))
+            .filter(|(_, influence)| *influence > 0.0)
+            .min_by(|(_, a), (_, b)| total_cmp_f32(*a, *b).reverse())
+            .map(|(name, _)| name.clone())
+    }
+
+    pub fn add_bonus_heuristic(
+        mut self,
+        heuristic: impl Fn(&Scheduler, TaskIdx) -> f32 + 'static,
+    ) -> Self {
+        self.bonus_heuristics.push(Box::new(heuristic));
+        self
+    }
+
+    pub fn get_total_task_hours(&self, task_idx: TaskIdx) -> f32 {
+        self.inner
+            .get(task_idx)
+            .map(|intervals| {
+                intervals
+                    .iter()
+                    .map(|interval| interval.hours())
+                    .sum::<f32>()
+            })
+            .unwrap_or(0.0)
+    }
+
+    pub fn get_task_hours_on(&self, task_idx: TaskIdx, interval: &Interval) -> f32 {
+        self.get(task_idx)
+            .into_iter()
+            .flatten()
+            .filter(|task_interval| task_interval.intercepts(interval))
+            .map(|task_interval| {
+                Interval::new(
+                    task_interval.start.max(interval.start),
+                    task_interval.end.min(interval.end),
+                )
+                .hours()
+            })
+            .sum::<f32>()
+    }
+
+    // the local day containing `timestamp`
+    pub fn get_day(&self, timestamp: Timestamp) -> Interval {
+        let date = timestamp.to_zoned(self.tz.clone()).date();
+        let [start, end] = [date, date.tomorrow().expect("Failed to get next day")].map(|date| {
+            date.to_zoned(self.tz.clone())
+                .expect("Failed to convert date to timestamp")
+                .timestamp()
+        });
+
+        Interval::new(start, end)
+    }
+
+    // the week containing `timestamp`, starting at local midnight of `week_start`
+    pub fn get_week(&self, timestamp: Timestamp) -> Interval {
+        let date = timestamp.to_zoned(self.tz.clone()).date();
+        let days_since_week_start = (date.weekday().to_monday_zero_offset()
+            - self.week_start.to_monday_zero_offset())
+        .rem_euclid(7);
+        let week_start = date - (days_since_week_start as i64).days();
+
+        let [start, end] = [week_start, week_start + 7.days()].map(|date| {
+            date.to_zoned(self.tz.clone())
+                .expect("Failed to convert date to timestamp")
+                .timestamp()
+        });
+
+        Interval::new(start, end)
+    }
+
+    pub fn get_planned_hours(&self, interval: Interval) -> f32 {
+        self.get_planned_hours_excluding(interval, &[])
+    }
+
+    // like `get_planned_hours`, leaving out plans of the `excluded` kinds
+    pub fn get_planned_hours_excluding(&self, interval: Interval, excluded: &[PlanKind]) -> f32 {
+        // plans can be changed directly, so any plan starting before the interval ends may reach
+        // into it
+        let end = Interval::new(interval.end, interval.end);
+        self.allocator
+            .plans
+            .range(..end)
+            .filter(|(plan, _)| plan.intercepts(&interval))
+            .filter(|(_, description)| !excluded.contains(&self.allocator.plan_kind(description)))
+            .map(|(plan, _)| plan.hours())
+            .sum::<f32>()
+    }
+
+    // hours of the interval that are neither outside of the working hours nor taken by plans,
+    // leaving out plans of the `excluded` kinds
+    pub fn get_free_hours_excluding(&self, interval: Interval, excluded: &[PlanKind]) -> f32 {
+        let end = Interval::new(interval.end, interval.end);
+        let mut blocked: Vec<_> = self
+            .allocator
+            .plans
+            .range(..end)
+            .filter(|(plan, _)| plan.intercepts(&interval))
+            .filter(|(_, description)| !excluded.contains(&self.allocator.plan_kind(description)))
+            .map(|(plan, _)| plan.clone())
+            .collect();
+        blocked.extend(self.allocator.outside_working_hours(&interval, &self.tz));
+        interval
+            .difference(&blocked)
+            .iter()
+            .map(Interval::hours)
+            .sum()
+    }
+
+    // renders each task as a row of `cols` time buckets spanning the scheduling interval. `#` marks
+    // buckets the task is scheduled in, `-` marks planned buckets and `.` marks free ones
+    pub fn to_gantt(&self, cols: usize) -> String {
+        let label_width = self
+            .tasks
+            .iter()
+            .map(|task| task.description.chars().count())
+            .max()
+            .unwrap_or(0);
+        let total_seconds = self.interval.end.as_second() - self.interval.start.as_second();
+        let buckets: Vec<_> = (0..cols as i64)
+            .map(|col| {
+                Interval::new(
+                    self.interval.start + (total_seconds * col / cols as i64).seconds(),
+                    self.interval.start + (total_seconds * (col + 1) / cols as i64).seconds(),
+                )
+            })
+            .collect();
+
+        let mut res = String::new();
+        for (task_idx, task) in self.tasks.iter().enumerate() {
+            let row: String = buckets
+                .iter()
+                .map(|bucket| {
+                    if self[task_idx].iter().any(|i| i.intercepts(bucket)) {
+                        '#'
+                    } else if self.allocator.plans.keys().any(|i| i.intercepts(bucket)) {
+                        '-'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            res += &format!("{:label_width$} |{}|\n", task.description, row);
+        }
+
+        res
+    }
+
+    pub fn is_exclusive_sibling_complete(&self, task_idx: TaskIdx) -> bool {
+        let Some(group) = self
+            .task(task_idx)
+            .and_then(|task| task.exclusive_group.as_ref())
+        else {
+            return false;
+        };
+
+        self.tasks.iter().enumerate().any(|(sibling_idx, sibling)| {
+            sibling_idx != task_idx
+                && sibling.exclusive_group.as_ref() == Some(group)
+                && sibling.volume - self.get_total_task_hours(sibling_idx) <= f32::EPSILON
+        })
+    }
+
+    // unfinished tasks with the number of unfinished tasks transitively depending on them, most
+    // blocking first
+    pub fn bottleneck_tasks(&self) -> Vec<(TaskIdx, usize)> {
+        let is_unfinished =
+            |idx: TaskIdx| self.tasks[idx].volume - self.get_total_task_hours(idx) > f32::EPSILON;
+        let mut dependents = vec![Vec::new(); self.tasks.len()];
+        for (idx, task) in self.tasks.iter().enumerate() {
+            for &dependency_idx in &task.dependencies {
+                if let Some(dependency_dependents) = dependents.get_mut(dependency_idx) {
+                    dependency_dependents.push(idx);
+                }
+            }
+        }
+
+        let mut bottlenecks: Vec<_> = (0..self.tasks.len())
+            .filter(|&idx| is_unfinished(idx))
+            .map(|idx| {
+                let mut visited = vec![false; self.tasks.len()];
+                let mut stack = dependents[idx].clone();
+                while let Some(dependent) = stack.pop() {
+                    if !mem::replace(&mut visited[dependent], true) {
+                        stack.extend(&dependents[dependent]);
+                    }
+                }
+                let count = (0..self.tasks.len())
+                    .filter(|&dependent| visited[dependent] && dependent != idx)
+                    .filter(|&dependent| is_unfinished(dependent))
+                    .count();
+                (idx, count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        bottlenecks.sort_by_key(|&(idx, count)| (Reverse(count), idx));
+
+        bottlenecks
+    }
+
+    // the tasks with the fields computed while parsing, for checking how a config was understood
+    pub fn export_tasks_with_computed_fields(&self) -> Vec<ResolvedTask> {
+        self.tasks
+            .iter()
+            .enumerate()
+            .map(|(idx, task)| ResolvedTask {
+                description: task.description.clone(),
+                deadline: task
+                    .deadline
+                    .to_zoned(self.tz.clone())
+                    .strftime("%F %R")
+                    .to_string(),
+                priority: task.priority,
+                remaining_volume: task.volume - self.get_total_task_hours(idx),
+                dependencies: task
+                    .dependencies
+                    .iter()
+                    .filter_map(|&dependency_idx| self.task(dependency_idx))
+                    .map(|dependency| dependency.description.clone())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    // problems with the tasks that make them impossible to schedule meaningfully
+    pub fn validate(&self) -> Vec<String> {
+        self.stale_deadlines()
+            .chain(self.unprioritized_stretch())
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    // everything `validate` reports, plus capped priorities, dependency cycles and deadlines that
+    // the free time cannot cover
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<_> = self
+            .stale_deadlines()
+            .chain(self.unprioritized_stretch())
+            .chain(self.capped_priorities())
+            .collect();
+        diagnostics.extend(self.dependency_cycles());
+        diagnostics.extend(self.oversubscribed_deadlines());
+        diagnostics
+    }
+
+    fn diagnostic(
+        &self,
+        code: &'static str,
+        severity: Severity,
+        task_idx: TaskIdx,
+        message: String,
+    ) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity,
+            task_idx: Some(task_idx),
+            task: Some(self.tasks[task_idx].description.clone()),
+            message,
+        }
+    }
+
+    fn stale_deadlines(&self) -> impl Iterator<Item = Diagnostic> + '_ {
+        self.tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| task.deadline <= self.interval.start)
+            .map(|(idx, task)| {
+                self.diagnostic(
+                    "deadline_before_start",
+                    Severity::Warning,
+                    idx,
+                    format!(
+                        "Task {} is due at {}, before the scheduling interval starts",
+                        task.description,
+                        task.deadline.to_zoned(self.tz.clone()).strftime("%F %R")
+                    ),
+                )
+            })
+    }
+
+    fn unprioritized_stretch(&self) -> impl Iterator<Item = Diagnostic> + '_ {
+        self.tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| task.stretch && task.priority <= 0.0)
+            .map(|(idx, task)| {
+                self.diagnostic(
+                    "stretch_without_priority",
+                    Severity::Warning,
+                    idx,
+                    format!(
+                        "Stretch task {} has no priority and gets none of the idle time",
+                        task.description
+                    ),
+                )
+            })
+    }
+
+    fn capped_priorities(&self) -> impl Iterator<Item = Diagnostic> + '_ {
+        let cap = self.priority_cap.unwrap_or(f32::INFINITY);
+        self.tasks
+            .iter()
+            .enumerate()
+            .filter(move |(_, task)| task.priority > cap)
+            .map(move |(idx, task)| {
+                self.diagnostic(
+                    "priority_capped",
+                    Severity::Warning,
+                    idx,
+                    format!(
+                        "Task {} has priority {}, counted as the cap of {}",
+                        task.description, task.priority, cap
+                    ),
+                )
+            })
+    }
+
+    // one diagnostic per cycle, on its lowest task index
+    fn dependency_cycles(&self) -> Vec<Diagnostic> {
+        fn visit(
+            tasks: &[Task],
+            idx: TaskIdx,
+            visited: &mut [bool],
+            path: &mut Vec<TaskIdx>,
+            cycles: &mut Vec<Vec<TaskIdx>>,
+        ) {
+            if let Some(position) = path.iter().position(|&on_path| on_path == idx) {
+                cycles.push(path[position..].to_vec());
+                return;
+            }
+            if visited[idx] {
+                return;
+            }
+            visited[idx] = true;
+            path.push(idx);
+            for &dependency_idx in &tasks[idx].dependencies {
+                if dependency_idx < tasks.len() {
+                    visit(tasks, dependency_idx, visited, path, cycles);
+                }
+            }
+            path.pop();
+        }
+
+        let mut visited = vec![false; self.tasks.len()];
+        let mut cycles = Vec::new();
+        for idx in 0..self.tasks.len() {
+            visit(&self.tasks, idx, &mut visited, &mut Vec::new(), &mut cycles);
+        }
+
+        cycles
+            .into_iter()
+            .map(|mut cycle| {
+                let lowest = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap_or(0);
+                cycle.rotate_left(lowest);
+                let names: Vec<_> = cycle
+                    .iter()
+                    .chain(cycle.first())
+                    .map(|&idx| self.tasks[idx].description.as_str())
+                    .collect();
+                self.diagnostic(
+                    "dependency_cycle",
+                    Severity::Error,
+                    cycle[0],
+                    format!(
+                        "Tasks depend on each other in a cycle: {}",
+                        names.join(" -> ")
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    // deadlines by which the unfinished tasks due need more hours than there is free time
+    fn oversubscribed_deadlines(&self) -> Vec<Diagnostic> {
+        let mut due: Vec<_> = (0..self.tasks.len())
+            .filter(|&idx| !self.tasks[idx].stretch && self.tasks[idx].deadline > self.current_time)
+            .collect();
+        due.sort_by_key(|&idx| self.tasks[idx].deadline);
+
+        let mut needed = 0.0;
+        let mut diagnostics = Vec::new();
+        for idx in due {
+            let task = &self.tasks[idx];
+            needed += (task.volume - self.get_total_task_hours(idx)).max(0.0);
+            let available = self.capacity_between(&Interval::new(self.current_time, task.deadline));
+            if needed - available > f32::EPSILON {
+                diagnostics.push(self.diagnostic(
+                    "oversubscribed",
+                    Severity::Warning,
+                    idx,
+                    format!(
+                        "Tasks due by {} need {} hour(s), but only {} are free",
+                        task.deadline.to_zoned(self.tz.clone()).strftime("%F %R"),
+                        needed,
+                        available
+                    ),
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    // unfinished tasks that got no time at all, with the reason why
+    pub fn get_unscheduled_tasks(&self) -> Vec<(TaskIdx, String)> {
+        self.tasks
+            .iter()
+            .enumerate()
+            .filter(|(idx, task)| task.volume > 0.0 && self[*idx].is_empty())
+            .filter(|(idx, _)| !self.is_exclusive_sibling_complete(*idx))
+            .map(|(idx, task)| {
+                let reason = if let Some(reason) = self.dropped.get(&idx) {
+                    reason.clone()
+                } else if task.priority == 0.0 {
+                    "zero priority".to_string()
+                } else if task.deadline <= self.interval.start {
+                    "deadline is before the scheduling interval".to_string()
+                } else {
+                    "no free time left".to_string()
+                };
+                (idx, reason)
+            })
+            .collect()
+    }
+
+    // changes that would let the tasks due by each oversubscribed deadline fit in the free time
+    // before it, from the start of the interval. for each such deadline, its task's deadline
+    // extended by how much it takes to fit, and the lowest priority task due by then whose work
+    // alone would cover the shortfall. works the same before and after scheduling
+    pub fn rebalance_priorities(&self) -> Vec<Suggestion> {
+        let start = self.interval.start;
+        let free_until = |end: Timestamp| {
+            let interval = Interval::new(start, end.clamp(start, self.interval.end));
+            self.get_free_hours_excluding(interval, &[])
+        };
+        let mut due: Vec<_> = (0..self.tasks.len())
+            .filter(|&idx| {
+                let task = &self.tasks[idx];
+                !task.stretch && task.priority > 0.0 && task.volume > 0.0 && task.deadline > start
+            })
+            .collect();
+        due.sort_by_key(|&idx| self.tasks[idx].deadline);
+
+        let mut needed = 0.0;
+        let mut suggestions: Vec<Suggestion> = Vec::new();
+        for (position, &idx) in due.iter().enumerate() {
+            let task = &self.tasks[idx];
+            needed += task.volume;
+            let shortfall = needed - free_until(task.deadline);
+            if shortfall <= f32::EPSILON {
+                continue;
+            }
+
+            let granularity = self.allocator.granularity;
+            let mut extended = task.deadline;
+            while extended < self.interval.end && free_until(extended) < needed - f32::EPSILON {
+                extended += granularity;
+            }
+            if free_until(extended) >= needed - f32::EPSILON {
+                let by = extended.duration_since(task.deadline);
+                suggestions.push(Suggestion {
+                    task_idx: idx,
+                    change: SuggestedChange::ExtendDeadline(by),
+                    message: format!(
+                        "Extend the deadline of {} by {:#} to fit the tasks due by then",
+                        task.description, by
+                    ),
+                });
+            }
+
+            let lowered = due[..=position]
+                .iter()
+                .copied()
+                .filter(|&idx| self.tasks[idx].volume >= shortfall - f32::EPSILON)
+                .min_by(|a, b| total_cmp_f32(self.tasks[*a].priority, self.tasks[*b].priority));
+            if let Some(lowered) = lowered {
+                let already_suggested = suggestions.iter().any(|suggestion| {
+                    suggestion.task_idx == lowered
+                        && suggestion.change == SuggestedChange::LowerPriority
+                });
+                if !already_suggested {
+                    suggestions.push(Suggestion {
+                        task_idx: lowered,
+                        change: SuggestedChange::LowerPriority,
+                        message: format!(
+                            "Lower the priority of {} or drop it to free {} hour(s) for the tasks \
+                             due by {}",
+                            self.tasks[lowered].description,
+                            self.tasks[lowered].volume,
+                            task.deadline.to_zoned(self.tz.clone()).strftime("%F %R")
+                        ),
+                    });
+                }
+            }
+        }
+        suggestions
+    }
+
+    pub fn get_missed_deadlines_tasks(&self) -> Vec<TaskIdx> {
+        self.tasks
+            .iter()
+            .enumerate()
+            .filter(|(idx, task)| {
+                // stretch tasks may get idle time on top of their volume
+                let left = task.volume - self.get_total_task_hours(*idx);
+                left > 0.0 || (left < 0.0 && !task.stretch)
+            })
+            .filter(|(idx, _)| !self.is_exclusive_sibling_complete(*idx))
+            .filter(|(idx, _)| !self.dropped.contains_key(idx))
             .map(|(idx, _)| idx)
             .collect()
     }
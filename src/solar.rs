@@ -0,0 +1,88 @@
+use jiff::{civil::Date, tz::TimeZone, Timestamp};
+use serde::Deserialize;
+
+// observer location in degrees, east and north are positive
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+const J2000: f64 = 2451545.0;
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2440587.5;
+
+// sunrise on `date` at `coordinates` using the sunrise equation, accurate to a minute or two.
+// `None` during polar day or night
+pub fn sunrise(date: Date, coordinates: &Coordinates) -> Option<Timestamp> {
+    let (transit, hour_angle) = solar_transit(date, coordinates)?;
+    from_julian_day(transit - hour_angle / 360.0)
+}
+
+pub fn sunset(date: Date, coordinates: &Coordinates) -> Option<Timestamp> {
+    let (transit, hour_angle) = solar_transit(date, coordinates)?;
+    from_julian_day(transit + hour_angle / 360.0)
+}
+
+// julian day of the solar noon and the hour angle of sunrise and sunset in degrees
+fn solar_transit(date: Date, coordinates: &Coordinates) -> Option<(f64, f64)> {
+    let days_since_epoch =
+        date.to_zoned(TimeZone::UTC).ok()?.timestamp().as_second() as f64 / 86400.0;
+    let days = (UNIX_EPOCH_JULIAN_DAY + 0.5 + days_since_epoch - J2000).round();
+
+    let mean_solar_time = days + 0.0009 - coordinates.longitude / 360.0;
+    let mean_anomaly = (357.5291 + 0.98560028 * mean_solar_time).rem_euclid(360.0);
+    let m = mean_anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (mean_anomaly + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit =
+        J2000 + mean_solar_time + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * 23.4397_f64.to_radians().sin()).asin();
+    let latitude = coordinates.latitude.to_radians();
+    let cos_hour_angle = ((-0.833_f64).to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+
+    Some((transit, cos_hour_angle.acos().to_degrees()))
+}
+
+fn from_julian_day(julian_day: f64) -> Option<Timestamp> {
+    Timestamp::from_second(((julian_day - UNIX_EPOCH_JULIAN_DAY) * 86400.0).round() as i64).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interval::Interval;
+    use jiff::{civil::date, ToSpan};
+
+    const LONDON: Coordinates = Coordinates {
+        latitude: 51.5074,
+        longitude: -0.1278,
+    };
+
+    #[test]
+    fn test_sunrise_sunset() {
+        // 04:43 and 21:21 local summer time on the solstice
+        let solstice = date(2025, 6, 21);
+        let day = Interval::new(
+            sunrise(solstice, &LONDON).unwrap(),
+            sunset(solstice, &LONDON).unwrap(),
+        );
+        let expected = Interval::new(
+            "2025-06-21T03:43Z".parse().unwrap(),
+            "2025-06-21T20:21Z".parse().unwrap(),
+        );
+        assert!(day.approx_eq(&expected, 3.minutes()), "{:?}", day);
+
+        let svalbard = Coordinates {
+            latitude: 78.2,
+            longitude: 15.6,
+        };
+        assert_eq!(sunset(solstice, &svalbard), None);
+    }
+}
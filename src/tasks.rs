@@ -1,25 +1,37 @@
+use crate::{
+    chrono::{from_chrono, to_chrono},
+    clock::Clock,
+    interval::{Interval, TimeWindow},
+    parse::{parse_span, parse_time_window, parse_timestamp_with_clock},
+};
+use croner::Cron;
 use derive_more::Into;
-use jiff::{civil::Date, tz::TimeZone, Timestamp};
-use std::error::Error;
+use indexmap::IndexMap;
+use jiff::{
+    civil::{Date, DateTime, Time},
+    tz::TimeZone,
+    Span, Timestamp, ToSpan, Unit,
+};
+use std::{error::Error, fmt, mem};
 
 impl TryFrom<String> for Task {
     type Error = Box<dyn Error>;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         let mut parts: Vec<_> = value.split('/').map(|p| p.trim()).collect();
+        // `1h/day` volumes are split in two by the separator
+        let per_day = parts.get(3) == Some(&"day");
+        if per_day {
+            parts.remove(3);
+        }
+        let mut parts = parts.into_iter();
 
-        let priority = if parts.len() == 5 {
-            let res = parts.pop().unwrap();
-            if !res.chars().all(|c| c == '!') {
-                return Err(format!("Invalid priority: {}", res).into());
-            }
-            res.len() as f32
-        } else {
-            1.0
-        };
-
-        let [description, deadline, volume, progress]: [&str; 4] =
-            parts.try_into().map_err(|e: Vec<_>| {
+        let [description, deadline, volume, progress]: [&str; 4] = parts
+            .by_ref()
+            .take(4)
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|e: Vec<_>| {
                 format!("Expected at least 4 elements, got {}: {:?}", e.len(), e)
             })?;
 
@@ -30,23 +42,227 @@ impl TryFrom<String> for Task {
         let volume = volume[..volume.len() - 1].parse::<u32>()? as f32;
         let progress = progress[..progress.len() - 1].parse::<u32>()? as f32;
 
-        Ok(Task {
+        let mut task = Task {
             description: description.to_string(),
             deadline,
-            priority,
+            priority: 1.0,
             volume: volume * (1.0 - progress / 100.0),
-            dependencies: Vec::new(),
-        })
+            ..Default::default()
+        };
+        if per_day {
+            task.daily_volume = Some(task.volume);
+        }
+
+        // optional trailing fields are either a priority of `!`s or `key=value` pairs, where `p`
+        // is a priority that need not be whole
+        for part in parts {
+            if let Some((key, value)) = part.split_once('=') {
+                match key.trim() {
+                    "p" => {
+                        let priority = value.trim().parse::<f32>()?;
+                        if !(priority.is_finite() && priority > 0.0) {
+                            return Err(format!(
+                                "Invalid priority: {}, expected a positive number",
+                                value.trim()
+                            )
+                            .into());
+                        }
+                        task.priority = priority;
+                    }
+                    "value" => task.value = Some(value.trim().parse::<f32>()?),
+                    "group" => task.exclusive_group = Some(value.trim().to_string()),
+                    "context" => task.context = Some(value.trim().to_string()),
+                    "every" => task.cadence = Some(value.trim().to_string()),
+                    "gran" => task.granularity = Some(parse_span(value)?),
+                    "stretch" => {
+                        task.stretch = true;
+                        if value.trim() != "true" {
+                            task.stretch_cap = Some(parse_span(value)?.total(Unit::Hour)? as f32);
+                        }
+                    }
+                    "contiguous" => task.contiguous_per_day = value.trim().parse::<bool>()?,
+                    "lead" => task.ideal_lead = Some(parse_span(value)?),
+                    "window_end" => {
+                        task.window_end = match value.trim() {
+                            "truncate" => WindowEnd::Truncate,
+                            "drop" => WindowEnd::Drop,
+                            value => {
+                                return Err(format!(
+                                    "Invalid window_end: {}, expected truncate or drop",
+                                    value
+                                )
+                                .into())
+                            }
+                        }
+                    }
+                    "requires" => {
+                        task.requires = value.split(',').map(|s| s.trim().to_string()).collect()
+                    }
+                    "after" => task.milestone = Some(value.trim().to_string()),
+                    "prefer" => task.preferred_window = Some(parse_time_window(value)?),
+                    "allow" => task.allowed_window = Some(parse_time_window(value)?),
+                    "weekly" => {
+                        task.weekly_budget = Some(parse_span(value)?.total(Unit::Hour)? as f32)
+                    }
+                    "tags" => task.tags = value.split(',').map(|s| s.trim().to_string()).collect(),
+                    key => {
+                        return Err(format!(
+                            "Unknown task field: {}, expected one of p, value, group, context, \
+                             every, gran, stretch, contiguous, lead, window_end, requires, after, \
+                             prefer, allow, weekly, tags",
+                            key
+                        )
+                        .into())
+                    }
+                }
+            } else if !part.is_empty() && part.chars().all(|c| c == '!') {
+                task.priority = part.len() as f32;
+            } else {
+                return Err(format!("Invalid priority: {}", part).into());
+            }
+        }
+
+        Ok(task)
+    }
+}
+
+// the slash separated form `Task::try_from` reads. the remaining volume is written as whole
+// hours with a progress, so fractions of an hour are rounded to the nearest percent. the
+// deadline is written as its local date
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let remaining = self.daily_volume.unwrap_or(self.volume);
+        let hours = remaining.ceil().max(0.0);
+        let progress = if hours > 0.0 {
+            ((1.0 - remaining / hours) * 100.0).round()
+        } else {
+            0.0
+        };
+        write!(
+            f,
+            "{} / {} / {}h{} / {}%",
+            self.description,
+            self.deadline.to_zoned(TimeZone::system()).date(),
+            hours,
+            if self.daily_volume.is_some() {
+                "/day"
+            } else {
+                ""
+            },
+            progress
+        )?;
+
+        if self.priority > 1.0 && self.priority.fract() == 0.0 {
+            write!(f, " / {}", "!".repeat(self.priority as usize))?;
+        } else if self.priority != 1.0 {
+            write!(f, " / p={}", self.priority)?;
+        }
+        if let Some(value) = self.value {
+            write!(f, " / value={}", value)?;
+        }
+        if let Some(group) = &self.exclusive_group {
+            write!(f, " / group={}", group)?;
+        }
+        if let Some(context) = &self.context {
+            write!(f, " / context={}", context)?;
+        }
+        if let Some(cadence) = &self.cadence {
+            write!(f, " / every={}", cadence)?;
+        }
+        if let Some(granularity) = self.granularity {
+            write!(f, " / gran={:#}", granularity)?;
+        }
+        if self.stretch {
+            match self.stretch_cap {
+                Some(cap) => write!(f, " / stretch={}h", cap)?,
+                None => write!(f, " / stretch=true")?,
+            }
+        }
+        if self.contiguous_per_day {
+            write!(f, " / contiguous=true")?;
+        }
+        if let Some(ideal_lead) = self.ideal_lead {
+            write!(f, " / lead={:#}", ideal_lead)?;
+        }
+        if self.window_end == WindowEnd::Drop {
+            write!(f, " / window_end=drop")?;
+        }
+        if !self.requires.is_empty() {
+            write!(f, " / requires={}", self.requires.join(","))?;
+        }
+        if let Some(milestone) = &self.milestone {
+            write!(f, " / after={}", milestone)?;
+        }
+        if let Some(window) = &self.preferred_window {
+            write!(f, " / prefer={}", window)?;
+        }
+        if let Some(window) = &self.allowed_window {
+            write!(f, " / allow={}", window)?;
+        }
+        if let Some(weekly_budget) = self.weekly_budget {
+            write!(f, " / weekly={}h", weekly_budget)?;
+        }
+        if !self.tags.is_empty() {
+            write!(f, " / tags={}", self.tags.join(","))?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct Task {
     pub description: String,
     pub deadline: Timestamp,
     pub priority: f32,
     pub volume: f32,
     pub dependencies: Vec<TaskIdx>,
+    // tasks that are better done first, but do not block this one
+    pub soft_dependencies: Vec<TaskIdx>,
+    // time of day the task is boosted in
+    pub preferred_window: Option<TimeWindow>,
+    // time of day the task may be scheduled in at all
+    pub allowed_window: Option<TimeWindow>,
+    // max hours of work on the task per week
+    pub weekly_budget: Option<f32>,
+    pub tags: Vec<String>,
+    // payoff of completing the task
+    pub value: Option<f32>,
+    // only one task of the group needs to be done
+    pub exclusive_group: Option<String>,
+    // location or tool the task needs, e.g. `office`
+    pub context: Option<String>,
+    // `daily`, `weekly`, `monthly` or a cron day, month and weekday part, e.g. `* * FRI`. a fresh
+    // instance is due at the start of each matching day
+    pub cadence: Option<String>,
+    // hours of work per day until the deadline, replaces `volume` once the window is known
+    pub daily_volume: Option<f32>,
+    // allocation block length overriding the allocator's granularities
+    pub granularity: Option<Span>,
+    // fills the idle time left after the other tasks instead of having a fixed volume
+    pub stretch: bool,
+    // max hours a stretch task gets
+    pub stretch_cap: Option<f32>,
+    // once started on a day, the task keeps going until that day's share is done
+    pub contiguous_per_day: bool,
+    // how long before the deadline the task should ideally be done, see `heuristics::lead_deadline`
+    pub ideal_lead: Option<Span>,
+    // what happens to a block cut short by the end of the scheduling window
+    pub window_end: WindowEnd,
+    // skills a resource needs to be assigned the task, see `SchedulerConfig::into_team`
+    pub requires: Vec<String>,
+    // name of a configured milestone the task cannot start before, see `Tasks::resolve_milestones`
+    pub milestone: Option<String>,
+    // the milestone's time once resolved
+    pub not_before: Option<Timestamp>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowEnd {
+    // the block ends with the window
+    #[default]
+    Truncate,
+    // the block is not scheduled and the task is dropped, e.g. for work that cannot be split
+    Drop,
 }
 
 pub type TaskIdx = usize;
@@ -60,18 +276,481 @@ impl TryFrom<Vec<Vec<String>>> for Tasks {
     fn try_from(value: Vec<Vec<String>>) -> Result<Self, Self::Error> {
         let mut tasks = Vec::new();
 
-        for task_chain in value {
-            let mut iter = task_chain.into_iter();
-            if let Some(task) = iter.next() {
-                tasks.push(task.try_into()?);
-            }
-            for task in iter {
-                let mut task: Task = task.try_into()?;
-                task.dependencies = vec![tasks.len() - 1];
-                tasks.push(task);
+        // each task depends on the tasks of the previous step of its chain. a task starting with
+        // `&` joins the step before it instead of starting a new one, so a chain can fan out to
+        // parallel tasks and back in to one that waits on all of them. a task starting with `~`
+        // only softly depends on the previous step, see `Task::soft_dependencies`
+        for (chain_idx, task_chain) in value.into_iter().enumerate() {
+            let mut previous_step = Vec::new();
+            let mut step = Vec::new();
+            for (idx, task) in task_chain.into_iter().enumerate() {
+                let (is_parallel, task_string) = match task.trim_start().strip_prefix('&') {
+                    Some(rest) => (true, rest.trim_start().to_string()),
+                    None => (false, task.clone()),
+                };
+                let (is_soft, task_string) = match task_string.trim_start().strip_prefix('~') {
+                    Some(rest) => (true, rest.trim_start().to_string()),
+                    None => (false, task_string),
+                };
+                let mut parsed: Task = task_string
+                    .try_into()
+                    .map_err(|err| format!("[{}][{}] '{}': {}", chain_idx, idx, task, err))?;
+                if !is_parallel && !step.is_empty() {
+                    previous_step = mem::take(&mut step);
+                }
+                if is_soft {
+                    parsed.soft_dependencies = previous_step.clone();
+                } else {
+                    parsed.dependencies = previous_step.clone();
+                }
+                step.push(tasks.len());
+                tasks.push(parsed);
             }
         }
 
         Ok(Tasks(tasks))
     }
 }
+
+impl Tasks {
+    // replaces each task with a cadence by one instance per period ending within `interval`
+    pub fn expand_recurring(&mut self, interval: &Interval) -> Result<(), Box<dyn Error>> {
+        for task_idx in 0..self.0.len() {
+            let Some(cadence) = &self.0[task_idx].cadence else {
+                continue;
+            };
+            let cron_part = match cadence.as_str() {
+                "daily" => "* * *",
+                "weekly" => "* * MON",
+                "monthly" => "1 * *",
+                cron_part => cron_part,
+            };
+            let cron = Cron::new(&format!("0 0 {}", cron_part)).parse()?;
+
+            // cron fields match local dates, which croner sees as utc midnights
+            let tz = TimeZone::system();
+            let first_day = interval.start.to_zoned(tz.clone()).date();
+            let mut deadlines = Vec::new();
+            for datetime in
+                cron.iter_from(to_chrono(first_day.to_zoned(TimeZone::UTC)?.timestamp()))
+            {
+                let day = from_chrono(datetime).to_zoned(TimeZone::UTC).date();
+                let deadline = day.to_zoned(tz.clone())?.timestamp();
+                if deadline > interval.end {
+                    break;
+                }
+                if deadline > interval.start {
+                    deadlines.push(deadline);
+                }
+            }
+            let Some(&first_deadline) = deadlines.first() else {
+                continue;
+            };
+
+            // each later instance waits until the previous period is over
+            self.0[task_idx].cadence = None;
+            let instance = self.0[task_idx].clone();
+            self.0.extend(deadlines.windows(2).map(|period| Task {
+                deadline: period[1],
+                not_before: Some(period[0]),
+                ..instance.clone()
+            }));
+            self.0[task_idx].deadline = first_deadline;
+        }
+
+        Ok(())
+    }
+
+    // sets the volume of tasks with a daily volume for each day left until the deadline in
+    // `interval`
+    pub fn resolve_daily_volumes(&mut self, interval: &Interval) {
+        for task in &mut self.0 {
+            let Some(daily_volume) = task.daily_volume else {
+                continue;
+            };
+            let until_deadline = task.deadline.min(interval.end);
+            let days = if until_deadline > interval.start {
+                Interval::new(interval.start, until_deadline)
+                    .daily(Time::midnight(), 24.hours(), &TimeZone::system())
+                    .len()
+            } else {
+                0
+            };
+            task.volume = daily_volume * days as f32;
+        }
+    }
+
+    // sets `not_before` of tasks waiting on a milestone to the milestone's time
+    pub fn resolve_milestones(
+        &mut self,
+        milestones: &IndexMap<String, Timestamp>,
+    ) -> Result<(), Box<dyn Error>> {
+        for task in &mut self.0 {
+            let Some(milestone) = &task.milestone else {
+                continue;
+            };
+            let not_before = milestones.get(milestone).ok_or_else(|| {
+                format!(
+                    "Unknown milestone: {}, in task {}",
+                    milestone, task.description
+                )
+            })?;
+            task.not_before = Some(task.not_before.map_or(*not_before, |t| t.max(*not_before)));
+        }
+        Ok(())
+    }
+}
+
+// reads a tab separated todo app export with `content`, `due`, `priority` and duration columns,
+// e.g. `Write report\t2025-03-05\tp1\t2h`. an optional header row is skipped. priorities `p1` to
+// `p4` map to 4.0 to 1.0, due dates may include a `%R` time
+pub fn tasks_from_tsv(tsv: &str) -> Result<Tasks, Box<dyn Error>> {
+    let mut tasks = Vec::new();
+
+    for (line_idx, line) in tsv.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let [content, due, priority, duration]: [&str; 4] = line
+            .split('\t')
+            .map(|column| column.trim())
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|e: Vec<_>| {
+                format!(
+                    "Expected 4 columns on line {}, got {}",
+                    line_idx + 1,
+                    e.len()
+                )
+            })?;
+        if line_idx == 0 && content.eq_ignore_ascii_case("content") {
+            continue;
+        }
+
+        let deadline = if due.contains(' ') {
+            DateTime::strptime("%F %R", due)?.to_zoned(TimeZone::system())?
+        } else {
+            due.parse::<Date>()?.to_zoned(TimeZone::system())?
+        }
+        .timestamp();
+        let priority = match priority.to_lowercase().as_str() {
+            "p1" => 4.0,
+            "p2" => 3.0,
+            "p3" => 2.0,
+            "p4" | "" => 1.0,
+            _ => return Err(format!("Invalid priority: {}", priority).into()),
+        };
+        let volume = parse_span(duration)?.total(Unit::Hour)? as f32;
+
+        tasks.push(Task {
+            description: content.to_string(),
+            deadline,
+            priority,
+            volume,
+            ..Default::default()
+        });
+    }
+
+    Ok(Tasks(tasks))
+}
+
+// subtracts the hours logged in a time tracker csv with `description`, `start` and `end` columns
+// from the matching tasks, e.g. `Write report,2025-03-05 09:00,2025-03-05 10:30`. an optional
+// header row is skipped, times are `now`, local `%F %R` or rfc 3339. a description shared by
+// several tasks is an error. returns warnings about time logged for unknown tasks
+pub fn reconcile_worked(
+    tasks: &mut [Task],
+    csv: &str,
+    clock: &dyn Clock,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let parse_time = |value| parse_timestamp_with_clock(value, clock);
+    let mut warnings = Vec::new();
+
+    for (line_idx, line) in csv.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let [description, start, end]: [&str; 3] = line
+            .split(',')
+            .map(|column| column.trim())
+            .collect::<Vec<_>>()
+            .try_into()
+            .map_err(|e: Vec<_>| {
+                format!(
+                    "Expected 3 columns on line {}, got {}",
+                    line_idx + 1,
+                    e.len()
+                )
+            })?;
+        if line_idx == 0 && description.eq_ignore_ascii_case("description") {
+            continue;
+        }
+
+        let worked = Interval::try_new(parse_time(start)?, parse_time(end)?)
+            .map_err(|err| format!("Line {}: {}", line_idx + 1, err))?;
+        let mut matching = tasks
+            .iter_mut()
+            .filter(|task| task.description == description);
+        match (matching.next(), matching.next()) {
+            (Some(task), None) => task.volume = (task.volume - worked.hours()).max(0.0),
+            (Some(_), Some(_)) => {
+                return Err(format!(
+                    "Line {}: Expected one task named {}, got several",
+                    line_idx + 1,
+                    description
+                )
+                .into())
+            }
+            (None, _) => warnings.push(format!(
+                "Logged {} hour(s) for unknown task: {}",
+                worked.hours(),
+                description
+            )),
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use jiff::civil::{date, time};
+
+    #[test]
+    fn test_expand_recurring() {
+        let mut tasks = Tasks(vec![
+            Task::try_from("Review finances / 2025-03-05 / 1h / 0% / every=weekly".to_string())
+                .unwrap(),
+            Task::try_from("Task / 2025-03-06 / 2h / 0%".to_string()).unwrap(),
+        ]);
+        let midnight = |day| {
+            date(2025, 3, day)
+                .to_zoned(TimeZone::system())
+                .unwrap()
+                .timestamp()
+        };
+        let interval = Interval::from_span(midnight(5), 504.hours());
+
+        tasks.expand_recurring(&interval).unwrap();
+
+        let instances: Vec<_> = tasks
+            .0
+            .iter()
+            .filter(|task| task.description == "Review finances")
+            .map(|task| (task.deadline, task.not_before))
+            .collect();
+        assert_eq!(
+            instances,
+            [
+                (midnight(10), None),
+                (midnight(17), Some(midnight(10))),
+                (midnight(24), Some(midnight(17)))
+            ]
+        );
+        assert_eq!(tasks.0.len(), 4);
+        assert_eq!(tasks.0[1].description, "Task");
+        assert!(tasks.0.iter().all(|task| task.cadence.is_none()));
+        assert!(tasks.0[2..].iter().all(|task| task.volume == 1.0));
+
+        // instances are plain tasks, so expanding again changes nothing
+        tasks.expand_recurring(&interval).unwrap();
+        assert_eq!(tasks.0.len(), 4);
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for (task, displayed) in [
+            ("Task / 2025-03-05 / 4h / 0%", "Task / 2025-03-05 / 4h / 0%"),
+            (
+                "Task / 2025-03-05 / 4h / 50% / !!",
+                "Task / 2025-03-05 / 2h / 0% / !!",
+            ),
+            (
+                "Task / 2025-03-05 / 4h / 33% / !",
+                "Task / 2025-03-05 / 3h / 11%",
+            ),
+            (
+                "Task / 2025-03-05 / 4h / 0% / p=1.5",
+                "Task / 2025-03-05 / 4h / 0% / p=1.5",
+            ),
+            (
+                "Task / 2025-03-05 / 4h / 0% / p=3",
+                "Task / 2025-03-05 / 4h / 0% / !!!",
+            ),
+            (
+                "Task / 2025-03-05 / 1h/day / 0%",
+                "Task / 2025-03-05 / 1h/day / 0%",
+            ),
+            (
+                "Task / 2025-03-05 / 4h / 0% / !!! / value=10 / group=food / context=office / \
+                 every=weekly / gran=30m / stretch=2h / contiguous=true / lead=1d / \
+                 window_end=drop / requires=design,review / after=freeze / prefer=09:00-12:00 / \
+                 allow=22:00-02:00 / weekly=4h / tags=writing,deep",
+                "Task / 2025-03-05 / 4h / 0% / !!! / value=10 / group=food / context=office / \
+                 every=weekly / gran=30m / stretch=2h / contiguous=true / lead=24h / \
+                 window_end=drop / requires=design,review / after=freeze / prefer=09:00-12:00 / \
+                 allow=22:00-02:00 / weekly=4h / tags=writing,deep",
+            ),
+        ] {
+            let first = Task::try_from(task.to_string()).unwrap().to_string();
+            assert_eq!(first, displayed);
+            let second = Task::try_from(first.clone()).unwrap().to_string();
+            assert_eq!(second, first);
+        }
+    }
+
+    #[test]
+    fn test_fan_out_chain() {
+        let tasks = Tasks::try_from(vec![vec![
+            "Research / 2025-03-05 / 2h / 0%".to_string(),
+            "Writing / 2025-03-06 / 3h / 0%".to_string(),
+            "& Slides / 2025-03-06 / 2h / 0%".to_string(),
+            "Rehearse / 2025-03-07 / 1h / 0%".to_string(),
+        ]])
+        .unwrap();
+
+        let dependencies: Vec<_> = tasks.0.iter().map(|task| &task.dependencies).collect();
+        assert_eq!(tasks.0[2].description, "Slides");
+        assert_eq!(dependencies, [&vec![], &vec![0], &vec![0], &vec![1, 2]]);
+    }
+
+    #[test]
+    fn test_soft_dependency_chain() {
+        let tasks = Tasks::try_from(vec![vec![
+            "Research / 2025-03-05 / 2h / 0%".to_string(),
+            "~ Writing / 2025-03-06 / 3h / 0%".to_string(),
+            "& ~ Slides / 2025-03-06 / 2h / 0%".to_string(),
+            "Rehearse / 2025-03-07 / 1h / 0%".to_string(),
+        ]])
+        .unwrap();
+
+        assert_eq!(tasks.0[1].description, "Writing");
+        assert_eq!(tasks.0[1].dependencies, []);
+        assert_eq!(tasks.0[1].soft_dependencies, [0]);
+        assert_eq!(tasks.0[2].description, "Slides");
+        assert_eq!(tasks.0[2].soft_dependencies, [0]);
+        // the next step still waits on the soft step
+        assert_eq!(tasks.0[3].dependencies, [1, 2]);
+        assert_eq!(tasks.0[3].soft_dependencies, []);
+    }
+
+    #[test]
+    fn test_task_optional_fields() {
+        let task =
+            Task::try_from("Task / 2025-03-05 / 4h / 50% / !! / value=10".to_string()).unwrap();
+        assert_eq!(task.priority, 2.0);
+        assert_eq!(task.volume, 2.0);
+        assert_eq!(task.value, Some(10.0));
+
+        let task = Task::try_from("Task / 2025-03-05 / 4h / 0% / p=1.5".to_string()).unwrap();
+        assert_eq!(task.priority, 1.5);
+        for priority in ["0", "-1", "inf", "NaN"] {
+            let task = format!("Task / 2025-03-05 / 4h / 0% / p={}", priority);
+            assert!(Task::try_from(task).is_err());
+        }
+
+        let task = Task::try_from("Task / 2025-03-05 / 4h / 0% / group=food".to_string()).unwrap();
+        assert_eq!(task.exclusive_group.as_deref(), Some("food"));
+
+        let task = Task::try_from("Task / 2025-03-05 / 4h / 0%".to_string()).unwrap();
+        assert_eq!(task.priority, 1.0);
+        assert_eq!(task.value, None);
+        assert_eq!(task.exclusive_group, None);
+
+        let task =
+            Task::try_from("Reading / 2025-03-05 / 0h / 0% / stretch=2h".to_string()).unwrap();
+        assert!(task.stretch);
+        assert_eq!(task.stretch_cap, Some(2.0));
+
+        let task =
+            Task::try_from("Writing / 2025-03-05 / 4h / 0% / contiguous=true".to_string()).unwrap();
+        assert!(task.contiguous_per_day);
+
+        let task = Task::try_from("Task / 2025-03-05 / 4h / 0% / gran=30m".to_string()).unwrap();
+        assert_eq!(task.granularity.unwrap().fieldwise(), 30.minutes());
+
+        let task = Task::try_from(
+            "Task / 2025-03-05 / 4h / 0% / prefer=09:00-12:00 / allow=08:00-20:00".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            task.preferred_window,
+            Some(TimeWindow::new(time(9, 0, 0, 0), time(12, 0, 0, 0)))
+        );
+        assert_eq!(
+            task.allowed_window,
+            Some(TimeWindow::new(time(8, 0, 0, 0), time(20, 0, 0, 0)))
+        );
+
+        let task = Task::try_from("Task / 2025-03-05 / 4h / 0% / weekly=90m".to_string()).unwrap();
+        assert_eq!(task.weekly_budget, Some(1.5));
+
+        let task =
+            Task::try_from("Task / 2025-03-05 / 4h / 0% / tags=writing, deep".to_string()).unwrap();
+        assert_eq!(task.tags, ["writing", "deep"]);
+
+        let err = Task::try_from("Task / 2025-03-05 / 4h / 0% / cost=1".to_string()).unwrap_err();
+        assert!(err.to_string().starts_with("Unknown task field: cost"));
+        assert!(Task::try_from("Task / 2025-03-05 / 4h / 0% / ?".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_tasks_from_tsv() {
+        let tsv = "content\tdue\tpriority\tduration\n\
+                   Write report\t2025-03-05\tp1\t2h\n\
+                   Buy milk\t2025-03-06 18:00\tp4\t30m\n";
+
+        let tasks: Vec<Task> = tasks_from_tsv(tsv).unwrap().into();
+
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].description, "Write report");
+        assert_eq!(tasks[0].priority, 4.0);
+        assert_eq!(tasks[0].volume, 2.0);
+        assert_eq!(tasks[1].description, "Buy milk");
+        assert_eq!(tasks[1].priority, 1.0);
+        assert_eq!(tasks[1].volume, 0.5);
+        assert!(tasks[0].deadline < tasks[1].deadline);
+
+        assert!(tasks_from_tsv("Task\t2025-03-05\tp9\t1h").is_err());
+    }
+
+    #[test]
+    fn test_reconcile_worked() {
+        let mut tasks =
+            vec![Task::try_from("Write report / 2025-03-07 / 4h / 0%".to_string()).unwrap()];
+        let clock = FixedClock("2025-03-05T16:00Z".parse().unwrap());
+        let csv = "description,start,end\n\
+                   Write report,2025-03-05 09:00,2025-03-05 10:30\n\
+                   Lunch,2025-03-05T12:00Z,2025-03-05T13:00Z\n\
+                   Write report,2025-03-05T15:00Z,now\n";
+
+        let warnings = reconcile_worked(&mut tasks, csv, &clock).unwrap();
+
+        assert_eq!(tasks[0].volume, 1.5);
+        assert_eq!(warnings, vec!["Logged 1 hour(s) for unknown task: Lunch"]);
+        assert!(reconcile_worked(&mut tasks, "Write report,2025-03-05 09:00", &clock).is_err());
+        let err = reconcile_worked(
+            &mut tasks,
+            "Write report,2025-03-05 10:00,2025-03-05 09:00",
+            &clock,
+        )
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Line 1: Expected the interval to end after it starts"));
+
+        tasks.push(tasks[0].clone());
+        let err = reconcile_worked(
+            &mut tasks,
+            "Write report,2025-03-05 09:00,2025-03-05 10:00",
+            &clock,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Line 1: Expected one task named Write report, got several"
+        );
+    }
+}
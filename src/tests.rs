@@ -1,10 +1,17 @@
 #![cfg(test)]
 use crate::{
-    allocators::TaskAllocatorWithPlans, heuristics, interval::Interval, scheduler::Scheduler,
-    tasks::Task,
+    allocators::{PlanKind, TaskAllocatorWithPlans},
+    heuristics,
+    interval::{Interval, TimeWindow},
+    json,
+    scheduler::{
+        best_task, total_cmp_f32, DeadlineRisk, Occupancy, PinConflict, Scheduler, StartRounding,
+        SuggestedChange,
+    },
+    tasks::{Task, WindowEnd},
 };
-use jiff::ToSpan;
-use std::collections::BTreeMap;
+use jiff::{civil::time, tz::TimeZone, SignedDuration, Timestamp, ToSpan};
+use std::{cmp::Ordering, collections::BTreeMap};
 
 pub fn get_test_scheduler() -> Scheduler {
     let tasks = vec![
@@ -14,6 +21,7 @@ pub fn get_test_scheduler() -> Scheduler {
             priority: 1.0,
             volume: 2.0,
             dependencies: vec![4],
+            ..Default::default()
         },
         Task {
             description: "Task 1".to_string(),
@@ -21,6 +29,7 @@ pub fn get_test_scheduler() -> Scheduler {
             priority: 1.0,
             volume: 1.0,
             dependencies: vec![0],
+            ..Default::default()
         },
         Task {
             description: "Task 2".to_string(),
@@ -28,6 +37,7 @@ pub fn get_test_scheduler() -> Scheduler {
             priority: 2.0,
             volume: 3.0,
             dependencies: vec![],
+            ..Default::default()
         },
         Task {
             description: "Task 3".to_string(),
@@ -35,6 +45,7 @@ pub fn get_test_scheduler() -> Scheduler {
             priority: 1.0,
             volume: 3.0,
             dependencies: vec![2],
+            ..Default::default()
         },
         Task {
             description: "Empty task".to_string(),
@@ -42,6 +53,7 @@ pub fn get_test_scheduler() -> Scheduler {
             priority: 1.0,
             volume: 0.0,
             dependencies: vec![],
+            ..Default::default()
         },
         Task {
             description: "Zero priority task".to_string(),
@@ -49,6 +61,7 @@ pub fn get_test_scheduler() -> Scheduler {
             priority: 0.0,
             volume: 0.5,
             dependencies: vec![],
+            ..Default::default()
         },
     ];
 
@@ -66,37 +79,33 @@ pub fn get_test_scheduler() -> Scheduler {
                 Interval::from_span("2025-03-05T22:00Z".parse().unwrap(), 2.hours()),
                 "".into(),
             ),
-        ]),
+        ])
+        .into(),
         granularity: 1.hour(),
+        ..Default::default()
     };
 
     let interval = Interval::from_span("2025-03-05T00:00Z".parse().unwrap(), 24.hours());
 
+    // the timestamps above are UTC, so days are too
     Scheduler::new(allocator, tasks, interval)
-        .add_heuristic(heuristics::dependency)
-        .add_heuristic(heuristics::volume)
-        .add_heuristic(heuristics::priority)
-        .add_heuristic(heuristics::deadline)
+        .with_time_zone(TimeZone::UTC)
+        .add_named_heuristic("dependency", heuristics::dependency)
+        .add_named_heuristic("volume", heuristics::volume)
+        .add_named_heuristic("priority", heuristics::priority)
+        .add_named_heuristic("deadline", heuristics::deadline)
 }
 
 #[test]
 fn test_scheduler() {
     let mut scheduler = get_test_scheduler();
 
-    while let Some((task_idx, task_interval)) = scheduler.next() {
+    while let Some((task_idx, task_interval)) = scheduler.next_block() {
         scheduler.schedule_task(task_idx, task_interval);
     }
 
-    let mut all_intervals = Vec::new();
-    for (task_idx, intervals) in scheduler.iter().enumerate() {
-        for interval in intervals {
-            all_intervals.push((task_idx, interval.clone()));
-        }
-    }
-    all_intervals.sort_by_key(|(_, interval)| interval.start);
-
     assert_eq!(
-        all_intervals,
+        scheduler.schedule_snapshot(),
         vec![
             (
                 2,
@@ -125,3 +134,1278 @@ fn test_scheduler() {
         ]
     );
 }
+
+#[test]
+fn test_contiguous_per_day() {
+    let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+    let get_scheduler = |contiguous_per_day| {
+        let tasks = vec![
+            Task {
+                description: "Contiguous".to_string(),
+                deadline: start + 24.hours(),
+                priority: 1.0,
+                volume: 3.0,
+                contiguous_per_day,
+                ..Default::default()
+            },
+            Task {
+                description: "Other".to_string(),
+                deadline: start + 24.hours(),
+                priority: 1.0,
+                volume: 3.0,
+                ..Default::default()
+            },
+        ];
+        let allocator = TaskAllocatorWithPlans {
+            granularity: 1.hour(),
+            ..Default::default()
+        };
+        Scheduler::new(allocator, tasks, Interval::from_span(start, 24.hours()))
+            .add_heuristic(heuristics::volume)
+    };
+
+    // the volume heuristic alternates between the two
+    let mut scheduler = get_scheduler(false);
+    scheduler.schedule();
+    assert_eq!(scheduler[0].len(), 3);
+
+    let mut scheduler = get_scheduler(true);
+    scheduler.schedule();
+    assert_eq!(scheduler[0], vec![Interval::from_span(start, 3.hours())]);
+    assert_eq!(
+        scheduler[1],
+        vec![Interval::from_span(start + 3.hours(), 3.hours())]
+    );
+
+    let mut scheduler = get_scheduler(true).with_reasons();
+    scheduler.schedule();
+    assert_eq!(
+        scheduler.reason(0, start + 1.hour()),
+        Some("continues the contiguous task started today")
+    );
+}
+
+#[test]
+fn test_gantt() {
+    let tasks = vec![
+        Task {
+            description: "Task 0".to_string(),
+            ..Default::default()
+        },
+        Task {
+            description: "Task 1".to_string(),
+            ..Default::default()
+        },
+    ];
+    let allocator = TaskAllocatorWithPlans {
+        granularity: 1.hour(),
+        ..Default::default()
+    };
+    let interval = Interval::from_span("2025-03-05T00:00Z".parse().unwrap(), 24.hours());
+    let mut scheduler = Scheduler::new(allocator, tasks, interval);
+
+    scheduler.schedule_task(
+        0,
+        Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 2.hours()),
+    );
+    scheduler.schedule_task(
+        1,
+        Interval::from_span("2025-03-05T15:00Z".parse().unwrap(), 1.hour()),
+    );
+
+    assert_eq!(
+        scheduler.to_gantt(24),
+        "Task 0 |.........##.............|\n\
+         Task 1 |...............#........|\n"
+    );
+}
+
+#[test]
+fn test_pin_wins() {
+    let mut scheduler = get_test_scheduler();
+    let pin = Interval::new(
+        "2025-03-05T14:00Z".parse().unwrap(),
+        "2025-03-05T16:00Z".parse().unwrap(),
+    );
+
+    scheduler
+        .pin_task(3, pin.clone(), PinConflict::PinWins)
+        .unwrap();
+
+    assert_eq!(scheduler[3], vec![pin.clone()]);
+    assert!(scheduler.allocator.plans.contains_key(&Interval::new(
+        "2025-03-05T13:00Z".parse().unwrap(),
+        "2025-03-05T14:00Z".parse().unwrap(),
+    )));
+    assert!(!scheduler
+        .allocator
+        .plans
+        .keys()
+        .any(|plan| plan.intercepts(&pin)));
+
+    scheduler.schedule();
+    for (task_idx, intervals) in scheduler.iter().enumerate() {
+        if task_idx != 3 {
+            assert!(!intervals.iter().any(|interval| interval.intercepts(&pin)));
+        }
+    }
+}
+
+#[test]
+fn test_plan_wins() {
+    let mut scheduler = get_test_scheduler();
+    let pin = Interval::new(
+        "2025-03-05T14:00Z".parse().unwrap(),
+        "2025-03-05T16:00Z".parse().unwrap(),
+    );
+
+    scheduler.pin_task(3, pin, PinConflict::PlanWins).unwrap();
+
+    assert_eq!(
+        scheduler[3],
+        vec![Interval::new(
+            "2025-03-05T15:00Z".parse().unwrap(),
+            "2025-03-05T16:00Z".parse().unwrap(),
+        )]
+    );
+    assert!(scheduler.allocator.plans.contains_key(&Interval::from_span(
+        "2025-03-05T13:00Z".parse().unwrap(),
+        2.hours()
+    )));
+}
+
+#[test]
+fn test_pin_conflict_error() {
+    let mut scheduler = get_test_scheduler();
+    let pin = Interval::new(
+        "2025-03-05T14:00Z".parse().unwrap(),
+        "2025-03-05T16:00Z".parse().unwrap(),
+    );
+
+    assert!(scheduler.pin_task(3, pin, PinConflict::Error).is_err());
+    assert!(scheduler[3].is_empty());
+
+    let pin = Interval::from_span("2025-03-05T15:00Z".parse().unwrap(), 1.hour());
+    assert!(scheduler.pin_task(3, pin, PinConflict::Error).is_ok());
+}
+
+#[test]
+fn test_overlapping_pins() {
+    let mut scheduler = get_test_scheduler();
+    // an unpinned block of another task under the pin is not the one reported
+    scheduler.schedule_task(
+        0,
+        Interval::from_span("2025-03-05T10:00Z".parse().unwrap(), 1.hour()),
+    );
+    let pin = Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 2.hours());
+    scheduler.pin_task(1, pin, PinConflict::PinWins).unwrap();
+
+    let pin = Interval::from_span("2025-03-05T10:00Z".parse().unwrap(), 2.hours());
+    let err = scheduler
+        .pin_task(3, pin, PinConflict::PinWins)
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Pinned tasks Task 3 and Task 1 overlap on 2025-03-05 10:00 - 11:00"
+    );
+    assert!(scheduler[3].is_empty());
+
+    // touching pins do not overlap
+    let pin = Interval::from_span("2025-03-05T11:00Z".parse().unwrap(), 1.hour());
+    assert!(scheduler.pin_task(3, pin, PinConflict::PinWins).is_ok());
+}
+
+#[test]
+fn test_estimated_completion() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.tasks[3].volume = 2.0;
+    scheduler.tasks[5].volume = 0.0;
+    assert_eq!(scheduler.remaining_volume_total(), 8.0);
+
+    // half of the work is done by 13:00, the rest fits into 15:00-19:00
+    scheduler.schedule_task(
+        2,
+        Interval::new(
+            "2025-03-05T09:00Z".parse().unwrap(),
+            "2025-03-05T12:00Z".parse().unwrap(),
+        ),
+    );
+    scheduler.schedule_task(
+        0,
+        Interval::from_span("2025-03-05T12:00Z".parse().unwrap(), 1.hour()),
+    );
+    scheduler.current_time = "2025-03-05T13:00Z".parse().unwrap();
+    assert_eq!(scheduler.remaining_volume_total(), 4.0);
+    assert_eq!(
+        scheduler.estimated_completion(),
+        Some("2025-03-05T19:00Z".parse().unwrap())
+    );
+
+    scheduler.tasks[3].volume = 20.0;
+    assert_eq!(scheduler.estimated_completion(), None);
+
+    scheduler.tasks[3].volume = 0.0;
+    scheduler.tasks[1].volume = 0.0;
+    scheduler.tasks[0].volume = 1.0;
+    assert_eq!(
+        scheduler.estimated_completion(),
+        Some("2025-03-05T13:00Z".parse().unwrap())
+    );
+}
+
+#[test]
+fn test_deadline_risk() {
+    let mut scheduler = get_test_scheduler();
+    // finishes at 10:00, 12 hours before its deadline
+    scheduler.tasks[1].deadline = "2025-03-05T22:00Z".parse().unwrap();
+    scheduler.schedule_task(
+        1,
+        Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 1.hour()),
+    );
+    assert_eq!(scheduler.deadline_risk(1), None);
+
+    scheduler.deadline_warning = 1.day();
+    assert_eq!(scheduler.deadline_risk(1), Some(DeadlineRisk::Amber));
+    scheduler.deadline_warning = 6.hours();
+    assert_eq!(scheduler.deadline_risk(1), None);
+
+    assert_eq!(scheduler.deadline_risk(0), Some(DeadlineRisk::Red));
+}
+
+#[test]
+fn test_frozen_zone() {
+    let mut scheduler = get_test_scheduler();
+    let frozen = Interval::from_span("2025-03-05T10:00Z".parse().unwrap(), 1.hour());
+    scheduler.schedule_task(2, frozen.clone());
+    scheduler.schedule_task(
+        0,
+        Interval::from_span("2025-03-05T15:00Z".parse().unwrap(), 1.hour()),
+    );
+    scheduler.frozen_until = Some("2025-03-05T10:30Z".parse().unwrap());
+
+    // the idle 09:00-10:00 is frozen too, so task 0 moves to the first gap after task 2
+    scheduler.compact();
+    assert_eq!(scheduler[2], vec![frozen.clone()]);
+    assert_eq!(
+        scheduler[0],
+        vec![Interval::from_span(
+            "2025-03-05T11:00Z".parse().unwrap(),
+            1.hour()
+        )]
+    );
+
+    scheduler.shift_all(1.hour()).unwrap();
+    assert_eq!(scheduler[2], vec![frozen.clone()]);
+    assert_eq!(
+        scheduler[0],
+        vec![Interval::from_span(
+            "2025-03-05T12:00Z".parse().unwrap(),
+            1.hour()
+        )]
+    );
+    assert_eq!(scheduler.get_last_task(), Some(0));
+
+    assert!(scheduler.shift_all((-3).hours()).is_err());
+    assert_eq!(scheduler[0][0].start, "2025-03-05T12:00Z".parse().unwrap());
+    let err = scheduler.shift_all(1.hour()).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Shifting by PT1H moves task Task 0 onto plan "
+    );
+
+    // nothing new is allocated in the frozen zone either
+    scheduler.current_time = scheduler.interval.start + 9.hours();
+    assert_eq!(
+        scheduler.allocator.allocate(&scheduler, 1).start,
+        "2025-03-05T11:00Z".parse().unwrap()
+    );
+
+    // a frozen block running past `frozen_until` stays in place and cannot be shifted onto
+    let mut scheduler = get_test_scheduler();
+    let start: Timestamp = "2025-03-05T09:00Z".parse().unwrap();
+    scheduler.schedule_task(2, Interval::from_span(start, 2.hours()));
+    scheduler.schedule_task(3, Interval::from_span(start + 2.hours(), 1.hour()));
+    scheduler.frozen_until = Some(start + 1.hour());
+    let err = scheduler.shift_all((-1).hours()).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "Shifting by -PT1H moves task Task 3 onto task Task 2, which stays in place"
+    );
+    assert_eq!(
+        scheduler[3],
+        vec![Interval::from_span(start + 2.hours(), 1.hour())]
+    );
+}
+
+#[test]
+fn test_schedule_only() {
+    let mut scheduler = get_test_scheduler();
+    // task 1 depends on task 0, which depends on the empty task 4
+    scheduler.schedule_only(1);
+
+    for (task_idx, intervals) in scheduler.iter().enumerate() {
+        assert_eq!(
+            !intervals.is_empty(),
+            [0, 1].contains(&task_idx),
+            "{}",
+            task_idx
+        );
+    }
+    assert_eq!(scheduler.get_total_task_hours(0), 2.0);
+    assert_eq!(scheduler.get_total_task_hours(1), 1.0);
+    assert_eq!(scheduler.heuristics.len(), 4);
+
+    // the rest can still be scheduled around them afterwards
+    assert_eq!(scheduler.current_time, scheduler.interval.start);
+    scheduler.schedule();
+    // tasks 0 and 1 took 09:00-12:00
+    assert_eq!(
+        scheduler[2][0],
+        Interval::from_span("2025-03-05T12:00Z".parse().unwrap(), 1.hour())
+    );
+}
+
+#[test]
+fn test_get_last_task() {
+    let mut scheduler = get_test_scheduler();
+    assert_eq!(scheduler.get_last_task(), None);
+
+    scheduler.schedule_task(
+        2,
+        Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 1.hour()),
+    );
+    scheduler.schedule_task(
+        0,
+        Interval::from_span("2025-03-05T10:00Z".parse().unwrap(), 1.hour()),
+    );
+    assert_eq!(scheduler.get_last_task(), Some(0));
+}
+
+#[test]
+fn test_trace() {
+    let mut scheduler = get_test_scheduler().with_trace();
+
+    let mut steps = Vec::new();
+    for _ in 0..5 {
+        let (task_idx, task_interval) = scheduler.next_block().unwrap();
+        scheduler.schedule_task(task_idx, task_interval.clone());
+        steps.push((task_idx, task_interval));
+    }
+
+    let trace = scheduler.take_trace();
+    assert_eq!(trace.len(), 5);
+    for (entry, (task_idx, task_interval)) in trace.iter().zip(steps) {
+        assert_eq!(entry.task_idx, task_idx);
+        assert_eq!(entry.interval, task_interval);
+        assert_eq!(entry.scores.len(), scheduler.tasks.len());
+        assert!(entry
+            .scores
+            .iter()
+            .all(|&score| score <= entry.scores[task_idx]));
+        assert!(entry.current_time <= entry.interval.start);
+    }
+    assert!(trace
+        .windows(2)
+        .all(|w| w[0].interval.end == w[1].current_time));
+    assert!(scheduler.take_trace().is_empty());
+}
+
+#[test]
+fn test_compact() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.schedule_task(
+        2,
+        Interval::from_span("2025-03-05T10:00Z".parse().unwrap(), 1.hour()),
+    );
+    scheduler.schedule_task(
+        3,
+        Interval::from_span("2025-03-05T20:00Z".parse().unwrap(), 1.hour()),
+    );
+    scheduler.schedule_task(
+        5,
+        Interval::from_span("2025-03-05T21:00Z".parse().unwrap(), 30.minutes()),
+    );
+
+    scheduler.compact();
+
+    assert_eq!(
+        scheduler[2],
+        vec![Interval::from_span(
+            "2025-03-05T09:00Z".parse().unwrap(),
+            1.hour()
+        )]
+    );
+    assert_eq!(
+        scheduler[3],
+        vec![Interval::from_span(
+            "2025-03-05T10:00Z".parse().unwrap(),
+            1.hour()
+        )]
+    );
+    assert_eq!(
+        scheduler[5],
+        vec![Interval::from_span(
+            "2025-03-05T11:00Z".parse().unwrap(),
+            30.minutes()
+        )]
+    );
+
+    // a task waiting on a milestone moves no earlier than the milestone, and one needing a
+    // context only into a gap starting while the context is available
+    let mut scheduler = get_test_scheduler();
+    scheduler.tasks[2].not_before = Some("2025-03-05T12:00Z".parse().unwrap());
+    scheduler.tasks[3].context = Some("office".to_string());
+    scheduler.contexts.insert(
+        Interval::from_span("2025-03-05T15:00Z".parse().unwrap(), 3.hours()),
+        "office".to_string(),
+    );
+    scheduler.schedule_task(
+        2,
+        Interval::from_span("2025-03-05T16:00Z".parse().unwrap(), 1.hour()),
+    );
+    scheduler.schedule_task(
+        3,
+        Interval::from_span("2025-03-05T20:00Z".parse().unwrap(), 1.hour()),
+    );
+
+    scheduler.compact();
+
+    assert_eq!(
+        scheduler[2],
+        vec![Interval::from_span(
+            "2025-03-05T12:00Z".parse().unwrap(),
+            1.hour()
+        )]
+    );
+    assert_eq!(
+        scheduler[3],
+        vec![Interval::from_span(
+            "2025-03-05T15:00Z".parse().unwrap(),
+            1.hour()
+        )]
+    );
+}
+
+#[test]
+fn test_compact_respects_dependencies_and_pins() {
+    let mut scheduler = get_test_scheduler();
+    scheduler
+        .pin_task(
+            2,
+            Interval::from_span("2025-03-05T11:00Z".parse().unwrap(), 1.hour()),
+            PinConflict::Error,
+        )
+        .unwrap();
+    scheduler.schedule_task(
+        3,
+        Interval::from_span("2025-03-05T20:00Z".parse().unwrap(), 1.hour()),
+    );
+
+    scheduler.compact();
+
+    assert_eq!(
+        scheduler[2],
+        vec![Interval::from_span(
+            "2025-03-05T11:00Z".parse().unwrap(),
+            1.hour()
+        )]
+    );
+    assert_eq!(
+        scheduler[3],
+        vec![Interval::from_span(
+            "2025-03-05T12:00Z".parse().unwrap(),
+            1.hour()
+        )]
+    );
+}
+
+#[test]
+fn test_exclusive_group() {
+    let mut scheduler = get_test_scheduler().add_heuristic(heuristics::exclusive_group);
+    scheduler.tasks[2].exclusive_group = Some("food".to_string());
+    scheduler.tasks[5].exclusive_group = Some("food".to_string());
+    scheduler.tasks[5].priority = 100.0;
+
+    assert_eq!(heuristics::exclusive_group(&scheduler, 2), 1.0);
+    scheduler.schedule();
+
+    assert!(scheduler.is_exclusive_sibling_complete(2));
+    assert_eq!(heuristics::exclusive_group(&scheduler, 2), 0.0);
+    assert!(scheduler[2].is_empty());
+    assert_eq!(scheduler.get_total_task_hours(5), 0.5);
+    assert!(!scheduler.get_missed_deadlines_tasks().contains(&2));
+}
+
+#[test]
+fn test_out_of_range_task_idx() {
+    let mut scheduler = get_test_scheduler();
+    let task_idx = scheduler.tasks.len();
+    let pin = Interval::from_span("2025-03-05T15:00Z".parse().unwrap(), 1.hour());
+
+    assert!(scheduler.task(task_idx).is_none());
+    assert!(scheduler
+        .pin_task(task_idx, pin, PinConflict::PinWins)
+        .is_err());
+    assert_eq!(heuristics::dependency(&scheduler, task_idx), 1.0);
+
+    scheduler.tasks[2].dependencies.push(task_idx);
+    assert_eq!(heuristics::dependency(&scheduler, 2), 1.0);
+}
+
+#[test]
+fn test_makespan() {
+    let mut scheduler = get_test_scheduler();
+    assert!(scheduler.makespan().is_none());
+
+    scheduler.schedule();
+
+    let last_end: Timestamp = "2025-03-05T18:00Z".parse().unwrap();
+    assert_eq!(
+        scheduler.makespan().unwrap().fieldwise(),
+        (last_end - scheduler.interval.start).fieldwise()
+    );
+    assert_eq!(
+        scheduler.earliest_finish(2),
+        Some("2025-03-05T13:00Z".parse().unwrap())
+    );
+    assert_eq!(scheduler.earliest_finish(0), None);
+}
+
+#[test]
+fn test_score_ordering() {
+    assert_eq!(total_cmp_f32(f32::NAN, f32::NEG_INFINITY), Ordering::Less);
+    assert_eq!(total_cmp_f32(f32::NAN, f32::NAN), Ordering::Equal);
+    assert_eq!(total_cmp_f32(-0.0, 0.0), Ordering::Equal);
+    assert_eq!(total_cmp_f32(f32::INFINITY, 1.0), Ordering::Greater);
+
+    assert_eq!(
+        best_task(&[f32::NAN, 1.0, f32::INFINITY, f32::INFINITY]),
+        Some(2)
+    );
+    assert_eq!(best_task(&[f32::NAN, f32::NEG_INFINITY]), Some(1));
+    assert_eq!(best_task(&[f32::NAN, f32::NAN]), Some(0));
+    assert_eq!(best_task(&[]), None);
+}
+
+#[test]
+fn test_pin_daily_review() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.interval = Interval::from_span(scheduler.interval.start, 72.hours());
+
+    let review_idx = scheduler
+        .pin_daily(
+            time(17, 45, 0, 0),
+            15.minutes(),
+            "Review",
+            PinConflict::PinWins,
+        )
+        .unwrap();
+    scheduler.schedule();
+
+    let reviews: Vec<_> = (0..3)
+        .map(|day| {
+            Interval::from_span(
+                scheduler.interval.start + (day * 24).hours().minutes(17 * 60 + 45),
+                15.minutes(),
+            )
+        })
+        .collect();
+    assert_eq!(scheduler[review_idx], reviews);
+    for (task_idx, intervals) in scheduler.iter().enumerate() {
+        if task_idx != review_idx {
+            assert!(!intervals
+                .iter()
+                .any(|interval| reviews.iter().any(|review| review.intercepts(interval))));
+        }
+    }
+    assert!(scheduler.clipped.is_empty());
+
+    // the last review runs past the end of the interval, so only its first half is pinned
+    let mut scheduler = get_test_scheduler();
+    scheduler.allocator.plans.clear();
+    let review_idx = scheduler
+        .pin_daily(time(23, 30, 0, 0), 1.hour(), "Review", PinConflict::PinWins)
+        .unwrap();
+    scheduler.schedule();
+
+    let review = Interval::from_span("2025-03-05T23:30Z".parse().unwrap(), 1.hour());
+    assert_eq!(
+        scheduler[review_idx],
+        vec![review.clip_to(&scheduler.interval).unwrap()]
+    );
+    assert_eq!(scheduler.clipped, vec![(review_idx, review)]);
+    assert!(!scheduler.get_missed_deadlines_tasks().contains(&review_idx));
+}
+
+#[test]
+fn test_drop_below() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.drop_below = 30.minutes();
+    scheduler.tasks[2].deadline = "2025-03-05T09:20Z".parse().unwrap();
+
+    scheduler.schedule();
+
+    assert!(scheduler[2].is_empty());
+    assert_eq!(
+        scheduler.dropped.get(&2).unwrap(),
+        "only 0.33 hour(s) available before the deadline"
+    );
+    assert!(!scheduler.get_missed_deadlines_tasks().contains(&2));
+    assert!(!scheduler[3].is_empty());
+}
+
+#[test]
+fn test_dominant_heuristic() {
+    let scheduler = get_test_scheduler();
+    let names = |scheduler: &Scheduler| {
+        scheduler
+            .heuristics
+            .iter()
+            .map(|(name, _, _)| name.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        names(&scheduler),
+        ["dependency", "volume", "priority", "deadline"]
+    );
+    // task 1 waits on task 0
+    assert_eq!(heuristics::dependency(&scheduler, 1), 0.0);
+    assert_eq!(
+        scheduler.dominant_heuristic(1).as_deref(),
+        Some("dependency")
+    );
+    assert_eq!(scheduler.dominant_heuristic(42), None);
+
+    // unnamed heuristics are named by position, and boosts count as much as suppression
+    let scheduler =
+        get_test_scheduler().add_heuristic(|_, task_idx| if task_idx == 2 { 1e3 } else { 1.0 });
+    assert_eq!(names(&scheduler)[4], "heuristic 4");
+    assert_eq!(
+        scheduler.dominant_heuristic(2).as_deref(),
+        Some("heuristic 4")
+    );
+
+    let scheduler = get_test_scheduler()
+        .add_heuristic(heuristics::just_in_time(0.5))
+        .add_named_heuristic("weighted_urgency", heuristics::weighted_urgency());
+    assert_eq!(names(&scheduler)[4..], ["heuristic 4", "weighted_urgency"]);
+    // task 5 has no priority, which the priority heuristic zeroes
+    assert_eq!(scheduler.dominant_heuristic(5).as_deref(), Some("priority"));
+}
+
+#[test]
+fn test_placement_reasons() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.schedule();
+    assert_eq!(scheduler.reasons, None);
+
+    let mut scheduler = get_test_scheduler().with_reasons();
+    scheduler.schedule();
+    let reasons: Vec<_> = scheduler
+        .timeline()
+        .map(|(task_idx, interval)| {
+            (
+                task_idx,
+                scheduler.reason(task_idx, interval.start).unwrap(),
+            )
+        })
+        .collect();
+    // task 0 runs right up to its 12:00 deadline
+    assert_eq!(reasons[1], (0, "placed here to meet its deadline"));
+    // nothing can start before the morning plan ends
+    assert_eq!(
+        reasons[0],
+        (2, "higher priority than the other tasks, first free slot")
+    );
+    assert_eq!(reasons[5], (3, "the only task that could run"));
+
+    let mut scheduler = get_test_scheduler()
+        .add_named_heuristic("prefer_started", heuristics::prefer_started(10.0))
+        .with_reasons();
+    scheduler.schedule();
+    let reasons: Vec<_> = scheduler
+        .timeline()
+        .map(|(task_idx, interval)| {
+            (
+                task_idx,
+                scheduler.reason(task_idx, interval.start).unwrap(),
+            )
+        })
+        .collect();
+    // boxed heuristics are reported by the name they were added with
+    assert_eq!(
+        reasons[2],
+        (3, "favoured by prefer_started, first free slot")
+    );
+
+    // built in heuristics are recognized under any name
+    let mut scheduler = get_test_scheduler().with_reasons();
+    scheduler.heuristics[3].0 = "urgency".to_string();
+    scheduler.schedule();
+    assert_eq!(
+        scheduler.reason(0, "2025-03-05T11:00Z".parse().unwrap()),
+        Some("placed here to meet its deadline")
+    );
+
+    // task 2 scores best but can not start before 11:00
+    let mut scheduler = get_test_scheduler().with_reasons();
+    scheduler.current_time = "2025-03-05T09:00Z".parse().unwrap();
+    scheduler.tasks[2].allowed_window = Some(TimeWindow::new(time(11, 0, 0, 0), time(20, 0, 0, 0)));
+    let (task_idx, interval) = scheduler.next_block().unwrap();
+    assert!(scheduler
+        .reason(task_idx, interval.start)
+        .unwrap()
+        .starts_with("the best scored task could not start yet"));
+
+    let mut scheduler = get_test_scheduler()
+        .add_bonus_heuristic(|_, task_idx| if task_idx == 0 { 100.0 } else { 0.0 })
+        .with_reasons();
+    scheduler.schedule();
+    let (_, first) = scheduler.timeline().next().unwrap();
+    assert_eq!(
+        scheduler.reason(0, first.start),
+        Some("nudged ahead by a bonus heuristic, first free slot")
+    );
+}
+
+#[test]
+fn test_placement_reasons_follow_blocks() {
+    let mut scheduler = get_test_scheduler().with_reasons();
+    scheduler.schedule();
+    let reasons = |scheduler: &Scheduler| {
+        scheduler
+            .timeline()
+            .map(|(task_idx, interval)| {
+                scheduler.reason(task_idx, interval.start).map(String::from)
+            })
+            .collect::<Vec<_>>()
+    };
+    let before = reasons(&scheduler);
+
+    // blocks right after a plan cannot shift onto it
+    assert!(scheduler.shift_all((-1).hours()).is_err());
+    assert_eq!(reasons(&scheduler), before);
+    scheduler.allocator.plans.clear();
+    scheduler.shift_all((-1).hours()).unwrap();
+    assert_eq!(reasons(&scheduler), before);
+
+    let pin = Interval::from_span("2025-03-05T20:00Z".parse().unwrap(), 1.hour());
+    scheduler
+        .pin_task(5, pin.clone(), PinConflict::Error)
+        .unwrap();
+    assert_eq!(scheduler.reason(5, pin.start), Some("pinned here"));
+}
+
+#[test]
+fn test_bonus_heuristics() {
+    let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+    let get_scheduler = || {
+        let tasks = vec![
+            Task {
+                description: "Local".to_string(),
+                deadline: start + 9.hours(),
+                priority: 1.0,
+                volume: 2.0,
+                ..Default::default()
+            },
+            Task {
+                description: "Urgent".to_string(),
+                deadline: start + 5.hours(),
+                priority: 1.0,
+                volume: 2.0,
+                ..Default::default()
+            },
+        ];
+        let allocator = TaskAllocatorWithPlans {
+            granularity: 1.hour(),
+            ..Default::default()
+        };
+        let mut scheduler =
+            Scheduler::new(allocator, tasks, Interval::from_span(start, 24.hours()))
+                .add_heuristic(heuristics::deadline);
+        scheduler.schedule_task(0, Interval::from_span(start, 1.hour()));
+        scheduler.current_time = start + 1.hour();
+        scheduler
+    };
+
+    // 1/8 * 4 for the local task beats 1/4 for the urgent one
+    let mut scheduler = get_scheduler().add_heuristic(heuristics::locality);
+    assert_eq!(scheduler.next_block().unwrap().0, 0);
+
+    // 1/8 + 0.1 for the local task does not
+    let mut scheduler = get_scheduler().add_bonus_heuristic(|scheduler, task_idx| {
+        (heuristics::locality(scheduler, task_idx) - 1.0) / 30.0
+    });
+    assert_eq!(scheduler.next_block().unwrap().0, 1);
+}
+
+#[test]
+fn test_gaps_before_deadline() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.current_time = "2025-03-05T10:00Z".parse().unwrap();
+
+    // task 3 is due at 18:00, past the 13:00 - 15:00 plan
+    assert_eq!(
+        scheduler.gaps_before_deadline(3),
+        vec![
+            Interval::new(
+                "2025-03-05T10:00Z".parse().unwrap(),
+                "2025-03-05T13:00Z".parse().unwrap(),
+            ),
+            Interval::new(
+                "2025-03-05T15:00Z".parse().unwrap(),
+                "2025-03-05T18:00Z".parse().unwrap(),
+            ),
+        ]
+    );
+    assert_eq!(scheduler.gaps_before_deadline(0).len(), 1);
+    assert!(scheduler.gaps_before_deadline(10).is_empty());
+}
+
+#[test]
+fn test_validate() {
+    let mut scheduler = get_test_scheduler();
+    assert!(scheduler.validate().is_empty());
+
+    scheduler.tasks[1].deadline = "2025-03-04T17:00Z".parse().unwrap();
+    assert_eq!(
+        scheduler.validate(),
+        vec!["Task Task 1 is due at 2025-03-04 17:00, before the scheduling interval starts"]
+    );
+}
+
+#[test]
+fn test_diagnostics() {
+    let mut scheduler = get_test_scheduler();
+    // tasks 0 and 2 need 5 hours by 13:00 with only 4 free, and task 3 is short enough to fit
+    scheduler.tasks[3].volume = 1.0;
+    scheduler.tasks[2].dependencies = vec![3];
+
+    let json = json::to_string(&scheduler.diagnostics()).unwrap();
+    assert_eq!(
+        json,
+        concat!(
+            r#"[{"code":"dependency_cycle","severity":"error","task_idx":2,"task":"Task 2","#,
+            r#""message":"Tasks depend on each other in a cycle: Task 2 -> Task 3 -> Task 2"},"#,
+            r#"{"code":"oversubscribed","severity":"warning","task_idx":2,"task":"Task 2","#,
+            r#""message":"Tasks due by 2025-03-05 13:00 need 5 hour(s), but only 4 are free"}]"#,
+        )
+    );
+}
+
+#[test]
+fn test_rebalance_priorities() {
+    let mut scheduler = get_test_scheduler();
+    // tasks 0 and 2 need 5 hours by 13:00 with only 4 free, and tasks 0 to 3 need 9 hours by
+    // 18:00 with only 7 free
+    let suggestions = scheduler.rebalance_priorities();
+    let messages: Vec<_> = suggestions.iter().map(|s| s.to_string()).collect();
+    assert_eq!(
+        messages,
+        [
+            "Extend the deadline of Task 2 by 3h to fit the tasks due by then",
+            "Lower the priority of Task 0 or drop it to free 2 hour(s) for the tasks due by \
+             2025-03-05 13:00",
+            "Extend the deadline of Task 3 by 2h to fit the tasks due by then",
+        ]
+    );
+    assert_eq!(suggestions[0].task_idx, 2);
+    assert_eq!(
+        suggestions[0].change,
+        SuggestedChange::ExtendDeadline(SignedDuration::from_hours(3))
+    );
+    assert_eq!(suggestions[1].change, SuggestedChange::LowerPriority);
+
+    scheduler.schedule();
+    assert_eq!(scheduler.rebalance_priorities(), suggestions);
+
+    scheduler.tasks[2].deadline += 3.hours();
+    scheduler.tasks[3].volume = 1.0;
+    assert_eq!(scheduler.rebalance_priorities(), []);
+
+    // time outside of the working hours is not free either
+    scheduler.allocator.working_hours = Some(TimeWindow::new(time(9, 0, 0, 0), time(12, 0, 0, 0)));
+    assert!(!scheduler.rebalance_priorities().is_empty());
+}
+
+#[test]
+fn test_bottleneck_tasks() {
+    let mut scheduler = get_test_scheduler();
+    // chain 2 -> 3 -> 0 -> 1, with the empty task 4 also blocking 0
+    scheduler.tasks[0].dependencies = vec![3, 4];
+
+    assert_eq!(scheduler.bottleneck_tasks(), vec![(2, 3), (3, 2), (0, 1)]);
+
+    scheduler.schedule_task(
+        2,
+        Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 3.hours()),
+    );
+    assert_eq!(scheduler.bottleneck_tasks(), vec![(3, 2), (0, 1)]);
+}
+
+#[test]
+fn test_timeline_consistency() {
+    let mut scheduler = get_test_scheduler();
+    let start: Timestamp = "2025-03-05T00:00Z".parse().unwrap();
+    let assert_consistent = |scheduler: &Scheduler| {
+        assert_eq!(
+            scheduler
+                .timeline()
+                .map(|(task_idx, interval)| (task_idx, interval.clone()))
+                .collect::<Vec<_>>(),
+            scheduler.schedule_snapshot()
+        );
+    };
+
+    for (task_idx, hour) in [(0, 20), (1, 9), (1, 10), (2, 15), (0, 21), (3, 11), (2, 16)] {
+        scheduler.schedule_task(
+            task_idx,
+            Interval::from_span(start + hour.hours(), 1.hour()),
+        );
+        assert_consistent(&scheduler);
+    }
+    // adjacent intervals of the same task are merged
+    assert_eq!(scheduler.timeline().count(), 4);
+
+    scheduler.compact();
+    assert_consistent(&scheduler);
+
+    // intervals of different tasks starting together are both kept
+    let mut scheduler = get_test_scheduler();
+    scheduler.schedule_task(0, Interval::from_span(start + 9.hours(), 1.hour()));
+    scheduler.schedule_task(1, Interval::from_span(start + 9.hours(), 2.hours()));
+    assert_consistent(&scheduler);
+    assert_eq!(scheduler.timeline().count(), 2);
+    assert_eq!(
+        scheduler.occupancy_at(start + 10.hours()),
+        Occupancy::Task(1)
+    );
+}
+
+#[test]
+fn test_fragmentation_score() {
+    let start: Timestamp = "2025-03-05T09:00Z".parse().unwrap();
+    let mut contiguous = get_test_scheduler();
+    assert_eq!(contiguous.fragmentation_score(), 1.0);
+    for hour in 0..3 {
+        contiguous.schedule_task(2, Interval::from_span(start + hour.hours(), 1.hour()));
+    }
+
+    // blocks of a whole granularity each are as good as it gets
+    let mut split = get_test_scheduler();
+    for hour in [0, 2, 6] {
+        split.schedule_task(2, Interval::from_span(start + hour.hours(), 1.hour()));
+    }
+
+    // 1.5 hours fit in 2 blocks, but take 3
+    let mut scattered = get_test_scheduler();
+    for hour in [0, 2, 6] {
+        scattered.schedule_task(2, Interval::from_span(start + hour.hours(), 30.minutes()));
+    }
+
+    assert_eq!(contiguous.fragmentation_score(), 1.0);
+    assert_eq!(split.fragmentation_score(), 1.0);
+    assert_eq!(scattered.fragmentation_score(), 1.5);
+
+    scattered.schedule_task(3, Interval::from_span(start + 8.hours(), 2.hours()));
+    assert_eq!(scattered.fragmentation_score(), 1.25);
+}
+
+#[test]
+fn test_first_feasible_deadline() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.current_time = "2025-03-05T11:00Z".parse().unwrap();
+
+    // 3 hours of task 2 from 11:00 get split by the 13:00 - 15:00 plan
+    assert_eq!(
+        scheduler.first_feasible_deadline(2),
+        Some("2025-03-05T16:00Z".parse().unwrap())
+    );
+
+    // lower priority work does not get in the way, higher priority work does
+    scheduler.schedule_task(
+        0,
+        Interval::from_span("2025-03-05T15:00Z".parse().unwrap(), 1.hour()),
+    );
+    assert_eq!(
+        scheduler.first_feasible_deadline(2),
+        Some("2025-03-05T16:00Z".parse().unwrap())
+    );
+    scheduler.tasks[0].priority = 2.0;
+    assert_eq!(
+        scheduler.first_feasible_deadline(2),
+        Some("2025-03-05T17:00Z".parse().unwrap())
+    );
+
+    scheduler.tasks[2].volume = 20.0;
+    assert_eq!(scheduler.first_feasible_deadline(2), None);
+}
+
+#[test]
+fn test_zero_volume_task_is_never_selected() {
+    // the empty task would win on heuristics alone
+    let mut scheduler = get_test_scheduler();
+    scheduler.heuristics.clear();
+    scheduler = scheduler.add_heuristic(|_, task_idx| if task_idx == 4 { 10.0 } else { 1.0 });
+
+    while let Some((task_idx, interval)) = scheduler.next_block() {
+        assert_ne!(task_idx, 4);
+        scheduler.schedule_task(task_idx, interval);
+    }
+    assert!(scheduler[4].is_empty());
+}
+
+#[test]
+fn test_occupancy_at() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.allocator.plans.insert_with_overriding(
+        Interval::from_span("2025-03-05T13:00Z".parse().unwrap(), 2.hours()),
+        "Meeting".to_string(),
+    );
+    scheduler.schedule_task(
+        2,
+        Interval::from_span("2025-03-05T09:00Z".parse().unwrap(), 2.hours()),
+    );
+    scheduler
+        .pin_task(
+            3,
+            Interval::from_span("2025-03-05T14:00Z".parse().unwrap(), 1.hour()),
+            PinConflict::PinWins,
+        )
+        .unwrap();
+    let at = |timestamp: &str| scheduler.occupancy_at(timestamp.parse().unwrap());
+
+    assert_eq!(at("2025-03-05T09:00Z"), Occupancy::Task(2));
+    assert_eq!(at("2025-03-05T10:59Z"), Occupancy::Task(2));
+    assert_eq!(at("2025-03-05T11:00Z"), Occupancy::Free);
+    assert_eq!(
+        at("2025-03-05T13:30Z"),
+        Occupancy::Plan("Meeting".to_string())
+    );
+    assert_eq!(at("2025-03-05T14:00Z"), Occupancy::Task(3));
+    assert_eq!(at("2025-03-05T08:59Z"), Occupancy::Plan("".to_string()));
+}
+
+#[test]
+fn test_fill_stretch() {
+    let mut scheduler = get_test_scheduler();
+    let stretch = |description: &str, priority| Task {
+        description: description.to_string(),
+        deadline: "2025-03-06T00:00Z".parse().unwrap(),
+        priority,
+        stretch: true,
+        ..Default::default()
+    };
+    let reading = scheduler.add_task(stretch("Reading", 1.0));
+    let learning = scheduler.add_task(stretch("Learning", 3.0));
+
+    while let Some((task_idx, interval)) = scheduler.next_block() {
+        scheduler.schedule_task(task_idx, interval);
+    }
+    assert!(scheduler[reading].is_empty());
+    let idle_hours: f32 = scheduler.idle_intervals().iter().map(Interval::hours).sum();
+    scheduler.fill_stretch();
+
+    assert_eq!(scheduler.get_total_task_hours(reading), idle_hours / 4.0);
+    assert_eq!(
+        scheduler.get_total_task_hours(learning),
+        idle_hours * 3.0 / 4.0
+    );
+    assert!(scheduler.idle_intervals().is_empty());
+    assert_eq!(scheduler.tasks[reading].volume, 0.0);
+    assert!(scheduler
+        .get_missed_deadlines_tasks()
+        .iter()
+        .all(|&idx| idx < reading));
+
+    let mut scheduler = get_test_scheduler();
+    let reading = scheduler.add_task(Task {
+        stretch_cap: Some(1.0),
+        ..stretch("Reading", 1.0)
+    });
+    let learning = scheduler.add_task(stretch("Learning", 1.0));
+    scheduler.schedule();
+    assert_eq!(scheduler.get_total_task_hours(reading), 1.0);
+    assert_eq!(scheduler.get_total_task_hours(learning), idle_hours - 1.0);
+
+    // stretch blocks keep to the task's allowed window, and a stretch task without priority is
+    // reported instead of silently getting nothing
+    let mut scheduler = get_test_scheduler();
+    let window = TimeWindow::new(time(20, 0, 0, 0), time(21, 0, 0, 0));
+    let reading = scheduler.add_task(Task {
+        allowed_window: Some(window.clone()),
+        ..stretch("Reading", 1.0)
+    });
+    let idle = scheduler.add_task(stretch("Idle", 0.0));
+    scheduler.schedule();
+    assert_eq!(scheduler.get_total_task_hours(reading), 1.0);
+    assert!(scheduler[reading]
+        .iter()
+        .all(|interval| window.complement(interval, &TimeZone::UTC).is_empty()));
+    assert!(scheduler[idle].is_empty());
+    assert_eq!(
+        scheduler.validate(),
+        vec!["Stretch task Idle has no priority and gets none of the idle time"]
+    );
+}
+
+#[test]
+fn test_schedule_snapshot_order() {
+    let mut scheduler = get_test_scheduler();
+    let start: Timestamp = "2025-03-05T09:00Z".parse().unwrap();
+    scheduler.schedule_task(3, Interval::from_span(start + 1.hour(), 1.hour()));
+    scheduler.schedule_task(2, Interval::from_span(start + 1.hour(), 1.hour()));
+    scheduler.schedule_task(1, Interval::from_span(start, 1.hour()));
+
+    assert_eq!(
+        scheduler.schedule_snapshot(),
+        vec![
+            (1, Interval::from_span(start, 1.hour())),
+            (2, Interval::from_span(start + 1.hour(), 1.hour())),
+            (3, Interval::from_span(start + 1.hour(), 1.hour())),
+        ]
+    );
+}
+
+#[test]
+fn test_start_rounding() {
+    let now: Timestamp = "2025-03-05T14:30Z".parse().unwrap();
+
+    assert_eq!(StartRounding::None.round(now, &TimeZone::UTC), now);
+    assert_eq!(
+        StartRounding::Hour.round(now, &TimeZone::UTC),
+        "2025-03-05T15:00Z".parse::<Timestamp>().unwrap()
+    );
+    assert_eq!(
+        StartRounding::Day.round(now, &TimeZone::UTC),
+        "2025-03-05T00:00Z".parse::<Timestamp>().unwrap()
+    );
+    let on_the_hour: Timestamp = "2025-03-05T15:00Z".parse().unwrap();
+    assert_eq!(
+        StartRounding::Hour.round(on_the_hour, &TimeZone::UTC),
+        on_the_hour
+    );
+}
+
+#[test]
+fn test_next_falls_back_to_runner_up() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.current_time = "2025-03-05T09:00Z".parse().unwrap();
+    assert_eq!(scheduler.next_block().unwrap().0, 2);
+
+    // task 2 can not start before 11:00 anymore
+    let mut scheduler = get_test_scheduler();
+    scheduler.current_time = "2025-03-05T09:00Z".parse().unwrap();
+    scheduler.tasks[2].allowed_window = Some(TimeWindow::new(time(11, 0, 0, 0), time(20, 0, 0, 0)));
+
+    let (task_idx, interval) = scheduler.next_block().unwrap();
+    assert_ne!(task_idx, 2);
+    assert_eq!(interval.start, "2025-03-05T09:00Z".parse().unwrap());
+}
+
+#[test]
+fn test_waits_for_allowed_window() {
+    let mut scheduler = get_test_scheduler().add_heuristic(heuristics::time_window);
+    let window = TimeWindow::new(time(16, 0, 0, 0), time(20, 0, 0, 0));
+    for task in &mut scheduler.tasks {
+        task.allowed_window = Some(window.clone());
+    }
+    scheduler.schedule();
+
+    // every task is outside of its window until 16:00, which ends nothing
+    let (_, first) = scheduler.timeline().next().unwrap();
+    assert_eq!(first.start, "2025-03-05T16:00Z".parse().unwrap());
+    assert!(scheduler
+        .timeline()
+        .all(|(_, interval)| interval.end <= "2025-03-05T20:00Z".parse().unwrap()));
+}
+
+#[test]
+fn test_waits_for_context() {
+    let mut scheduler = get_test_scheduler().add_heuristic(heuristics::context);
+    for task in &mut scheduler.tasks {
+        task.context = Some("office".to_string());
+    }
+    let office = Interval::from_span("2025-03-05T16:00Z".parse().unwrap(), 3.hours());
+    scheduler
+        .contexts
+        .insert(office.clone(), "office".to_string());
+    scheduler.schedule();
+
+    // nothing can run before the office is available, which ends nothing
+    let (_, first) = scheduler.timeline().next().unwrap();
+    assert_eq!(first.start, office.start);
+}
+
+#[test]
+fn test_ends_when_nothing_is_held_back() {
+    let mut scheduler = get_test_scheduler().add_heuristic(heuristics::daily_volume);
+    scheduler.interval = Interval::from_span(scheduler.interval.start, (24 * 365).hours());
+    for task in &mut scheduler.tasks {
+        task.priority = 0.0;
+        task.daily_volume = Some(1.0);
+    }
+
+    // no task has used up its daily volume, so waiting for the next day cannot help
+    assert_eq!(scheduler.next_block(), None);
+    assert_eq!(scheduler.current_time, scheduler.interval.start);
+}
+
+#[test]
+fn test_soft_plans() {
+    let mut scheduler = get_test_scheduler();
+    let interval = scheduler.interval.clone();
+    let afternoon = Interval::from_span("2025-03-05T13:00Z".parse().unwrap(), 2.hours());
+    scheduler.allocator.plans.insert(afternoon, "Gym".into());
+    scheduler.allocator.soft_plans.insert("Gym".into());
+
+    assert_eq!(scheduler.get_planned_hours(interval.clone()), 13.0);
+    assert_eq!(
+        scheduler.get_planned_hours_excluding(interval, &[PlanKind::Soft]),
+        11.0
+    );
+    // 18 hours until task 3's deadline, of which only the first plan is hard
+    assert_eq!(heuristics::deadline(&scheduler, 3), 1.0 / 9.0);
+}
+
+#[test]
+fn test_custom_selector() {
+    let mut scheduler = get_test_scheduler();
+    scheduler.select = Box::new(|scores, _| scores.iter().position(|score| *score > 0.0));
+
+    // task 2 has the best score, but task 0 is the first one that can be scheduled at all
+    let (task_idx, interval) = scheduler.next_block().unwrap();
+    assert_eq!(task_idx, 0);
+    assert_eq!(interval.start, "2025-03-05T09:00Z".parse().unwrap());
+
+    scheduler.select = Box::new(|_, _| None);
+    assert!(scheduler.next_block().is_none());
+}
+
+#[test]
+fn test_window_end() {
+    let scheduler_at_end = |window_end| {
+        let mut scheduler = get_test_scheduler();
+        scheduler.interval = Interval::from_span(scheduler.interval.start, 21.hours().minutes(30));
+        scheduler.current_time = "2025-03-05T21:00Z".parse().unwrap();
+        // the only task left with time before its deadline
+        scheduler.tasks[3].deadline = "2025-03-06T02:00Z".parse().unwrap();
+        scheduler.tasks[3].window_end = window_end;
+        scheduler
+    };
+
+    let mut scheduler = scheduler_at_end(WindowEnd::Truncate);
+    assert_eq!(
+        scheduler.next_block(),
+        Some((
+            3,
+            Interval::from_span("2025-03-05T21:00Z".parse().unwrap(), 30.minutes())
+        ))
+    );
+
+    let mut scheduler = scheduler_at_end(WindowEnd::Drop);
+    assert_eq!(scheduler.next_block(), None);
+    assert!(scheduler.dropped.contains_key(&3));
+    assert_eq!(scheduler.get_total_task_hours(3), 0.0);
+}